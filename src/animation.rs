@@ -0,0 +1,311 @@
+use std::rc::Rc;
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::ecs::World;
+use crate::schedule::Time;
+use crate::transform::Transform;
+
+// must match MAX_JOINTS in shaders/skinning.glsl
+pub const MAX_JOINTS: usize = 64;
+
+pub struct Joint {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub rest: Transform,    // relative to the parent joint, the pose without any animation
+    pub inverse_bind: Mat4, // from model space to the joint's space in the pose the mesh was bound in
+}
+
+// the joints of a skinned mesh, indexed by the joint indices of its vertices
+pub struct Skeleton {
+    joints: Vec<Joint>,
+    root: Mat4,        // the model space transform of the joints without a parent
+    order: Vec<usize>, // joint indices with every parent before its children
+}
+
+impl Skeleton {
+    pub fn new(joints: Vec<Joint>, root: Mat4) -> Result<Self, String> {
+        if joints.len() > MAX_JOINTS {
+            return Err(format!("skeleton has {} joints, at most {} are supported", joints.len(), MAX_JOINTS));
+        }
+
+        // repeatedly take the joints whose parent was already taken
+        let mut order = Vec::with_capacity(joints.len());
+        let mut placed = vec![false; joints.len()];
+        while order.len() < joints.len() {
+            let before = order.len();
+            for (i, joint) in joints.iter().enumerate() {
+                if !placed[i] && joint.parent.is_none_or(|parent| placed.get(parent) == Some(&true)) {
+                    placed[i] = true;
+                    order.push(i);
+                }
+            }
+            if order.len() == before {
+                return Err("skeleton joints have a cycle or a missing parent".to_string());
+            }
+        }
+
+        Ok(Skeleton { joints, root, order })
+    }
+
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    pub fn rest_pose(&self) -> Vec<Transform> {
+        self.joints.iter().map(|joint| joint.rest).collect()
+    }
+
+    // the skinning matrix of every joint for `pose`, moving vertices from the bind pose to `pose`
+    pub fn joint_matrices(&self, pose: &[Transform], out: &mut Vec<Mat4>) {
+        let mut globals = vec![Mat4::IDENTITY; self.joints.len()];
+        for &i in &self.order {
+            let parent = self.joints[i].parent.map_or(self.root, |parent| globals[parent]);
+            globals[i] = parent * pose[i].matrix();
+        }
+
+        out.clear();
+        out.extend(globals.iter().zip(&self.joints).map(|(global, joint)| *global * joint.inverse_bind));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,   // holds each keyframe until the next
+    Linear, // lerps translation and scale, slerps rotation
+}
+
+#[derive(Clone, Debug)]
+pub enum Keyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+// keyframes animating one property of one joint
+#[derive(Clone, Debug)]
+pub struct Channel {
+    pub joint: String, // matched to skeleton joints by name, so clips can be shared between skeletons
+    pub times: Vec<f32>, // ascending, one per keyframe
+    pub keyframes: Keyframes,
+    pub interpolation: Interpolation,
+}
+
+impl Channel {
+    // the keyframes surrounding `time` and how far between them it is
+    fn keys(&self, time: f32) -> (usize, usize, f32) {
+        let next = self.times.partition_point(|key| *key <= time);
+        if next == 0 {
+            return (0, 0, 0.);
+        }
+        if next == self.times.len() {
+            return (next - 1, next - 1, 0.);
+        }
+        let previous = next - 1;
+        let t = match self.interpolation {
+            Interpolation::Step => 0.,
+            Interpolation::Linear => (time - self.times[previous]) / (self.times[next] - self.times[previous]),
+        };
+        (previous, next, t)
+    }
+
+    fn sample(&self, time: f32, pose: &mut Transform) {
+        if self.times.is_empty() {
+            return;
+        }
+        let (a, b, t) = self.keys(time);
+        match &self.keyframes {
+            Keyframes::Translation(values) => pose.set_translation(values[a].lerp(values[b], t)),
+            Keyframes::Rotation(values) => pose.set_rotation(values[a].slerp(values[b], t)),
+            Keyframes::Scale(values) => pose.set_scale(values[a].lerp(values[b], t)),
+        }
+    }
+}
+
+pub struct AnimationClip {
+    pub name: String,
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    // a clip lasting until its last keyframe
+    pub fn new(name: &str, channels: Vec<Channel>) -> Self {
+        let duration = channels.iter().filter_map(|channel| channel.times.last()).fold(0., |a: f32, b| a.max(*b));
+        AnimationClip {
+            name: name.to_string(),
+            duration,
+            channels,
+        }
+    }
+}
+
+// a clip being played, with how much it contributes to the blended pose
+#[derive(Clone)]
+struct Layer {
+    clip: Rc<AnimationClip>,
+    targets: Vec<Option<usize>>, // the skeleton joint of each channel
+    time: f32,
+    looping: bool,
+    weight: f32,
+    target_weight: f32,
+    fade_speed: f32, // weight per second, infinite to jump to the target weight
+}
+
+// plays and blends animation clips on a skeleton, producing the joint matrices of a skinned object
+#[derive(Clone)]
+pub struct AnimationPlayer {
+    skeleton: Rc<Skeleton>,
+    layers: Vec<Layer>,
+    pub speed: f32,
+    joint_matrices: Vec<Mat4>,
+}
+
+impl AnimationPlayer {
+    pub fn new(skeleton: Rc<Skeleton>) -> Self {
+        let mut joint_matrices = Vec::new();
+        skeleton.joint_matrices(&skeleton.rest_pose(), &mut joint_matrices);
+        AnimationPlayer {
+            skeleton,
+            layers: Vec::new(),
+            speed: 1.,
+            joint_matrices,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn skeleton(&self) -> &Rc<Skeleton> {
+        &self.skeleton
+    }
+
+    fn layer(&mut self, clip: &Rc<AnimationClip>, looping: bool) -> &mut Layer {
+        let index = match self.layers.iter().position(|layer| Rc::ptr_eq(&layer.clip, clip)) {
+            Some(index) => index,
+            None => {
+                let targets = clip.channels.iter().map(|channel| self.skeleton.joint_index(&channel.joint)).collect();
+                self.layers.push(Layer {
+                    clip: clip.clone(),
+                    targets,
+                    time: 0.,
+                    looping,
+                    weight: 0.,
+                    target_weight: 0.,
+                    fade_speed: f32::INFINITY,
+                });
+                self.layers.len() - 1
+            },
+        };
+        let layer = &mut self.layers[index];
+        layer.looping = looping;
+        layer
+    }
+
+    // plays `clip` from the start, stopping every other clip
+    #[allow(dead_code)]
+    pub fn play(&mut self, clip: &Rc<AnimationClip>, looping: bool) {
+        self.layers.retain(|layer| Rc::ptr_eq(&layer.clip, clip));
+        let layer = self.layer(clip, looping);
+        layer.time = 0.;
+        layer.weight = 1.;
+        layer.target_weight = 1.;
+        layer.fade_speed = f32::INFINITY;
+    }
+
+    // fades `clip` in and every other clip out over `duration` seconds
+    #[allow(dead_code)]
+    pub fn crossfade(&mut self, clip: &Rc<AnimationClip>, duration: f32, looping: bool) {
+        let fade_speed = if duration > 0. { 1. / duration } else { f32::INFINITY };
+        for layer in self.layers.iter_mut() {
+            layer.target_weight = 0.;
+            layer.fade_speed = fade_speed;
+        }
+        let layer = self.layer(clip, looping);
+        if layer.weight == 0. {
+            layer.time = 0.;
+        }
+        layer.target_weight = 1.;
+        layer.fade_speed = fade_speed;
+    }
+
+    // plays `clip` with a fixed `weight` alongside the other clips, for blending like walk and run by speed
+    // the pose is the weighted average of every playing clip, a weight of 0 stops the clip
+    #[allow(dead_code)]
+    pub fn blend(&mut self, clip: &Rc<AnimationClip>, weight: f32, looping: bool) {
+        let layer = self.layer(clip, looping);
+        layer.weight = weight.max(0.);
+        layer.target_weight = layer.weight;
+        layer.fade_speed = f32::INFINITY;
+    }
+
+    #[allow(dead_code)]
+    pub fn stop(&mut self) {
+        self.layers.clear();
+    }
+
+    // whether `clip` is contributing to the pose
+    #[allow(dead_code)]
+    pub fn is_playing(&self, clip: &Rc<AnimationClip>) -> bool {
+        self.layers.iter().any(|layer| Rc::ptr_eq(&layer.clip, clip))
+    }
+
+    // advances every clip and fade, and updates the joint matrices
+    pub fn update(&mut self, delta_time: f32) {
+        for layer in self.layers.iter_mut() {
+            layer.time += delta_time * self.speed;
+            if layer.looping && layer.clip.duration > 0. {
+                layer.time = layer.time.rem_euclid(layer.clip.duration);
+            } else {
+                layer.time = layer.time.clamp(0., layer.clip.duration);
+            }
+
+            let step = layer.fade_speed * delta_time;
+            if layer.weight < layer.target_weight {
+                layer.weight = (layer.weight + step).min(layer.target_weight);
+            } else {
+                layer.weight = (layer.weight - step).max(layer.target_weight);
+            }
+        }
+        self.layers.retain(|layer| layer.weight > 0. || layer.target_weight > 0.);
+
+        // blending each clip in by its share of the weight so far averages them all
+        let rest = self.skeleton.rest_pose();
+        let mut pose = rest.clone();
+        let mut total_weight = 0.;
+        for layer in &self.layers {
+            if layer.weight <= 0. {
+                continue;
+            }
+            let mut layer_pose = rest.clone();
+            for (channel, target) in layer.clip.channels.iter().zip(&layer.targets) {
+                if let Some(joint) = target {
+                    channel.sample(layer.time, &mut layer_pose[*joint]);
+                }
+            }
+
+            total_weight += layer.weight;
+            let t = layer.weight / total_weight;
+            for (joint, layer_joint) in pose.iter_mut().zip(&layer_pose) {
+                *joint = joint.lerp(layer_joint, t);
+            }
+        }
+
+        self.skeleton.joint_matrices(&pose, &mut self.joint_matrices);
+    }
+
+    // the skinning matrix of each joint, for u_joint_matrices
+    pub fn joint_matrices(&self) -> &[Mat4] {
+        &self.joint_matrices
+    }
+}
+
+// advances every AnimationPlayer component by the frame's delta time
+pub fn update_animations(world: &mut World) {
+    let delta = world.resource::<Time>().map_or(0., |time| time.delta);
+    for (_, player) in world.query_mut::<AnimationPlayer>() {
+        player.update(delta);
+    }
+}
@@ -0,0 +1,251 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, rc::Rc, time::{Duration, Instant, SystemTime}};
+
+use glam::Vec3;
+use sdl2::{image::{ImageRWops, LoadSurface}, pixels::PixelFormatEnum, rwops::RWops, surface::Surface};
+
+use crate::graphics::*;
+use crate::material::{Material, MaterialHandle};
+use crate::mesh::{MeshCache, MeshData, MeshHandle};
+use crate::preprocessor::{ShaderPreprocessor, SHADER_DIR};
+
+// how often hot reloading checks shader files for changes, statting every file each frame adds up
+const RELOAD_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+// index of a program in Assets, the default handle refers to the engine's default program
+pub struct ProgramHandle(pub(crate) usize);
+
+// identifies a compiled permutation of a vertex and fragment shader
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ProgramKey {
+    vert: String,
+    frag: String,
+    defines: Vec<String>, // sorted, so the order defines are passed in doesn't matter
+}
+
+impl ProgramKey {
+    fn new(vert: &str, frag: &str, defines: &[&str]) -> Self {
+        let mut defines: Vec<String> = defines.iter().map(|define| define.to_string()).collect();
+        defines.sort();
+        ProgramKey {
+            vert: vert.to_string(),
+            frag: frag.to_string(),
+            defines,
+        }
+    }
+}
+
+// every file a program was built from, and when it was last modified
+struct ProgramFiles {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl ProgramFiles {
+    fn new(paths: impl Iterator<Item = PathBuf>) -> Self {
+        ProgramFiles {
+            files: paths.map(|path| {
+                let modified = modified_time(&path);
+                (path, modified)
+            }).collect(),
+        }
+    }
+
+    // records the current modification times, returning whether any changed since the last call
+    fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, modified) in self.files.iter_mut() {
+            let current = modified_time(path);
+            changed |= current != *modified;
+            *modified = current;
+        }
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+struct ProgramEntry {
+    program: Program,
+    key: Option<ProgramKey>, // None for programs added already compiled
+    files: ProgramFiles,
+}
+
+// owns the gpu resources shared between objects, which refer to them by handle
+pub struct Assets {
+    programs: Vec<ProgramEntry>,
+    permutations: HashMap<ProgramKey, ProgramHandle>,
+    preprocessor: ShaderPreprocessor,
+    materials: Vec<Material>,
+    meshes: MeshCache,
+    textures: HashMap<PathBuf, Rc<Texture>>,
+    hot_reload: bool,
+    last_poll: Instant,
+}
+
+impl Assets {
+    // with `hot_reload`, shaders are read from the source tree instead of the binary, and programs are
+    // recompiled when any file they were built from changes, see `reload_changed_programs`
+    pub fn new(hot_reload: bool) -> Result<Self, String> {
+        let preprocessor = if hot_reload {
+            ShaderPreprocessor::from_dir(Path::new(SHADER_DIR))
+        } else {
+            ShaderPreprocessor::embedded()
+        };
+
+        let mut assets = Assets {
+            programs: Vec::new(),
+            permutations: HashMap::new(),
+            preprocessor,
+            materials: Vec::new(),
+            meshes: MeshCache::new(),
+            textures: HashMap::new(),
+            hot_reload,
+            last_poll: Instant::now(),
+        };
+
+        let default_program = assets.load_program("default.vert", "default.frag", &[])?;
+        assets.add_material(Material::colored(default_program, Vec3::ONE));
+
+        Ok(assets)
+    }
+
+    // a program compiled by the game itself, which isn't hot reloaded
+    pub fn add_program(&mut self, program: Program) -> ProgramHandle {
+        self.programs.push(ProgramEntry { program, key: None, files: ProgramFiles::new(std::iter::empty()) });
+        ProgramHandle(self.programs.len() - 1)
+    }
+
+    // compiles the permutation of `vert` and `frag` with `defines`, or returns it if it was already compiled
+    pub fn load_program(&mut self, vert: &str, frag: &str, defines: &[&str]) -> Result<ProgramHandle, String> {
+        let key = ProgramKey::new(vert, frag, defines);
+        if let Some(handle) = self.permutations.get(&key) {
+            return Ok(*handle);
+        }
+
+        let (program, files) = self.preprocessor.compile(vert, frag, defines)?;
+        let files = ProgramFiles::new(files.iter().filter_map(|name| self.preprocessor.path(name)));
+        self.programs.push(ProgramEntry { program, key: Some(key.clone()), files });
+
+        let handle = ProgramHandle(self.programs.len() - 1);
+        self.permutations.insert(key, handle);
+        Ok(handle)
+    }
+
+    // the shader `name` with its includes expanded, for programs compiled from sources in memory
+    pub fn shader_source(&self, name: &str) -> Result<String, String> {
+        Ok(self.preprocessor.preprocess(name, &[])?.source)
+    }
+
+    pub fn program(&self, handle: ProgramHandle) -> &Program {
+        &self.programs[handle.0].program
+    }
+
+    // recompiles programs built from files that changed since they were last compiled
+    // a program that fails to compile keeps its previous version, and the compile log is printed
+    // called every frame, but only looks at the files every RELOAD_POLL_INTERVAL
+    pub fn reload_changed_programs(&mut self) {
+        if !self.hot_reload || self.last_poll.elapsed() < RELOAD_POLL_INTERVAL {
+            return;
+        }
+        self.last_poll = Instant::now();
+
+        for entry in self.programs.iter_mut() {
+            let Some(key) = &entry.key else { continue };
+            if !entry.files.poll() {
+                continue;
+            }
+
+            let defines: Vec<&str> = key.defines.iter().map(|define| define.as_str()).collect();
+            match self.preprocessor.compile(&key.vert, &key.frag, &defines) {
+                Ok((program, files)) => {
+                    // includes may have been added or removed
+                    entry.files = ProgramFiles::new(files.iter().filter_map(|name| self.preprocessor.path(name)));
+                    entry.program = program;
+                    println!("Reloaded {} + {}", key.vert, key.frag);
+                },
+                Err(e) => {
+                    println!("Couldn't reload {} + {}, keeping the previous program:\n{}", key.vert, key.frag, e);
+                },
+            }
+        }
+    }
+
+    pub fn add_material(&mut self, material: Material) -> MaterialHandle {
+        self.materials.push(material);
+        MaterialHandle(self.materials.len() - 1)
+    }
+
+    pub fn material(&self, handle: MaterialHandle) -> &Material {
+        &self.materials[handle.0]
+    }
+
+    // for changing a material's params after it's created, every object using it sees the change
+    #[allow(dead_code)]
+    pub fn material_mut(&mut self, handle: MaterialHandle) -> &mut Material {
+        &mut self.materials[handle.0]
+    }
+
+    // the mesh cached under `name`, generating and uploading it with `create` if there isn't one
+    pub fn add_mesh(&mut self, name: &str, create: impl FnOnce() -> MeshData) -> MeshHandle {
+        self.meshes.get_or_create(name, create)
+    }
+
+    #[allow(dead_code)]
+    pub fn mesh(&self, name: &str) -> Option<MeshHandle> {
+        self.meshes.get(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_mesh(&mut self, name: &str) -> bool {
+        self.meshes.remove(name)
+    }
+
+    // cached meshes stay uploaded after the last object using them despawns, until this frees them
+    #[allow(dead_code)]
+    pub fn clear_unused_meshes(&mut self) {
+        self.meshes.clear_unused();
+    }
+
+    // loads an image file as a repeating, mipmapped texture, or returns it if it was already loaded
+    pub fn load_texture(&mut self, path: &Path) -> Result<Rc<Texture>, String> {
+        if let Some(texture) = self.textures.get(path) {
+            return Ok(texture.clone());
+        }
+        let surface = Surface::from_file(path).map_err(|e| format!("Couldn't load {}: {}", path.display(), e))?;
+        self.add_texture(path, surface)
+    }
+
+    // decodes an image held in memory, like one embedded in a model file, cached under `name`
+    pub fn load_texture_from_memory(&mut self, name: &str, bytes: &[u8]) -> Result<Rc<Texture>, String> {
+        if let Some(texture) = self.textures.get(Path::new(name)) {
+            return Ok(texture.clone());
+        }
+        let surface = RWops::from_bytes(bytes)
+            .and_then(|rwops| rwops.load())
+            .map_err(|e| format!("Couldn't load {}: {}", name, e))?;
+        self.add_texture(Path::new(name), surface)
+    }
+
+    fn add_texture(&mut self, key: &Path, surface: Surface) -> Result<Rc<Texture>, String> {
+        let surface = surface.convert_format(PixelFormatEnum::ABGR8888)
+            .map_err(|e| format!("Couldn't convert {}: {}", key.display(), e))?;
+        let (width, height) = (surface.width() as usize, surface.height() as usize);
+        let pitch = surface.pitch() as usize;
+
+        // images store the top row first, opengl expects the bottom row first
+        let pixels: Vec<u8> = surface.with_lock(|pixels| {
+            pixels.chunks(pitch).take(height).rev().flat_map(|row| &row[..width * 4]).copied().collect()
+        });
+
+        let texture = Texture::from_rgba(width as i32, height as i32, &pixels)
+            .map_err(|e| format!("{}: {}", key.display(), e))?;
+        texture.repeat();
+        texture.generate_mipmaps();
+
+        let texture = Rc::new(texture);
+        self.textures.insert(key.to_path_buf(), texture.clone());
+        Ok(texture)
+    }
+}
@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+
+use crate::ecs::{Entity, World};
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+// changes to the world queued while it's borrowed, like from inside a query, and applied later
+// the schedule applies them at the end of every stage, and changes to entities despawned by then do nothing
+#[derive(Default)]
+pub struct Commands {
+    queue: RefCell<Vec<Command>>,
+}
+
+impl Commands {
+    // queues any change to the world
+    pub fn add(&self, command: impl FnOnce(&mut World) + 'static) {
+        self.queue.borrow_mut().push(Box::new(command));
+    }
+
+    // queues spawning an entity with the components added to the returned builder
+    pub fn spawn(&self) -> EntityCommands<'_> {
+        EntityCommands { commands: self, entity: None, changes: Vec::new() }
+    }
+
+    // queues changes to the components of `entity`
+    #[allow(dead_code)]
+    pub fn entity(&self, entity: Entity) -> EntityCommands<'_> {
+        EntityCommands { commands: self, entity: Some(entity), changes: Vec::new() }
+    }
+
+    pub fn despawn(&self, entity: Entity) {
+        self.add(move |world| { world.despawn(entity); });
+    }
+
+    // despawns the entity and all of its descendants
+    #[allow(dead_code)]
+    pub fn despawn_recursive(&self, entity: Entity) {
+        self.add(move |world| { world.despawn_recursive(entity); });
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.queue.borrow().is_empty()
+    }
+
+    // runs the queued commands in order, commands they queue run too
+    pub(crate) fn apply(world: &mut World) {
+        loop {
+            let queue = world.commands().queue.take();
+            if queue.is_empty() {
+                break;
+            }
+            for command in queue {
+                command(world);
+            }
+        }
+    }
+}
+
+type Change = Box<dyn FnOnce(&mut World, Entity)>;
+
+// component changes to one entity, queued together when the builder is dropped
+pub struct EntityCommands<'c> {
+    commands: &'c Commands,
+    entity: Option<Entity>, // None to spawn a new one
+    changes: Vec<Change>,
+}
+
+impl EntityCommands<'_> {
+    pub fn insert<T: 'static>(mut self, component: T) -> Self {
+        self.changes.push(Box::new(move |world, entity| world.insert(entity, component)));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn remove<T: 'static>(mut self) -> Self {
+        self.changes.push(Box::new(|world, entity| { world.remove::<T>(entity); }));
+        self
+    }
+}
+
+impl Drop for EntityCommands<'_> {
+    fn drop(&mut self) {
+        let target = self.entity;
+        let changes = std::mem::take(&mut self.changes);
+        self.commands.add(move |world| {
+            let entity = match target {
+                Some(entity) if !world.is_alive(entity) => return,
+                Some(entity) => entity,
+                None => world.spawn(),
+            };
+            for change in changes {
+                change(world, entity);
+            }
+        });
+    }
+}
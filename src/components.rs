@@ -0,0 +1,48 @@
+use glam::{Mat4, Vec4};
+
+use crate::ecs::{Entity, World};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+// for finding entities by name and identifying them in debug output
+pub struct Name(pub String);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// tint multiplied with the material's color, white if an entity has none
+pub struct Color(pub Vec4);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+// entities without one both cast and receive shadows
+pub struct Shadows {
+    pub cast: bool,
+    pub receive: bool,
+}
+
+impl Default for Shadows {
+    fn default() -> Self {
+        Shadows { cast: true, receive: true }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// the resource the frame is rendered from
+pub struct Camera {
+    pub projection_matrix: Mat4,
+    pub view_matrix: Mat4,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            projection_matrix: Mat4::IDENTITY,
+            view_matrix: Mat4::IDENTITY,
+        }
+    }
+}
+
+impl World {
+    // the first entity with `name`
+    #[allow(dead_code)]
+    pub fn find(&self, name: &str) -> Option<Entity> {
+        self.query::<Name>().find(|(_, other)| other.0 == name).map(|(entity, _)| entity)
+    }
+}
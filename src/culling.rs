@@ -0,0 +1,85 @@
+use glam::{Mat4, Vec3, Vec4};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// axis-aligned bounding box
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    // the smallest box containing every point, or an empty box at the origin if there are none
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else {
+            return Aabb { min: Vec3::ZERO, max: Vec3::ZERO };
+        };
+        let mut aabb = Aabb { min: *first, max: *first };
+        for point in points {
+            aabb.min = aabb.min.min(*point);
+            aabb.max = aabb.max.max(*point);
+        }
+        aabb
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        [
+            Vec3::new(self.min.x, self.min.y, self.min.z),
+            Vec3::new(self.max.x, self.min.y, self.min.z),
+            Vec3::new(self.min.x, self.max.y, self.min.z),
+            Vec3::new(self.max.x, self.max.y, self.min.z),
+            Vec3::new(self.min.x, self.min.y, self.max.z),
+            Vec3::new(self.max.x, self.min.y, self.max.z),
+            Vec3::new(self.min.x, self.max.y, self.max.z),
+            Vec3::new(self.max.x, self.max.y, self.max.z),
+        ]
+    }
+
+    // the box containing this box after transforming it by `matrix`
+    pub fn transformed(&self, matrix: &Mat4) -> Self {
+        let corners = self.corners().map(|corner| matrix.transform_point3(corner));
+        Aabb::from_points(&corners)
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+}
+
+// the six planes bounding what a camera can see, normals pointing inwards
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    // extracts the planes from a combined projection * view matrix (gribb and hartmann)
+    pub fn from_view_projection(matrix: &Mat4) -> Self {
+        let rows = [matrix.row(0), matrix.row(1), matrix.row(2), matrix.row(3)];
+        let planes = [
+            rows[3] + rows[0], // left
+            rows[3] - rows[0], // right
+            rows[3] + rows[1], // bottom
+            rows[3] - rows[1], // top
+            rows[3] + rows[2], // near, conservative for projections with a [0, 1] depth range too
+            rows[3] - rows[2], // far, degenerate for infinite projections
+        ];
+        Frustum { planes }
+    }
+
+    // false only if the box is entirely outside one of the planes
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        for plane in &self.planes {
+            let normal = plane.truncate();
+            if normal.length_squared() < f32::EPSILON {
+                continue; // the far plane of an infinite projection
+            }
+
+            // the corner furthest along the plane's normal
+            let positive = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            if normal.dot(positive) + plane.w < 0. {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -1,96 +1,95 @@
-use std::collections::HashMap;
-use glam::{Mat4, Vec3, Vec4, Quat};
-use sdl2::event::Event;
+use std::path::Path;
+use std::time::Duration;
+
+use glam::{Mat4, Vec3};
 use rand::Rng;
 
-use crate::object::Object;
 use crate::game::Game;
+use crate::light::Light;
+use crate::assets::{Assets, ProgramHandle};
+use crate::components::{Camera, Shadows};
+use crate::ecs::{Entity, World};
+use crate::gamepad::Gamepads;
+use crate::input::{InputMap, InputState};
+use crate::material::{Material, MaterialHandle};
+use crate::mesh::MeshHandle;
+use crate::meshgen;
+use crate::physics::{Collider, Collisions, RigidBody};
+use crate::random::Random;
+use crate::schedule::{Schedule, Stage, Time};
+use crate::transform::Transform;
+
+pub struct Dropper;
+
+// used when there's no input.cfg in the working directory
+const DEFAULT_BINDINGS: &str = "axis move_x = key:A/key:D, key:Left/key:Right, pad:dpleft/pad:dpright, padaxis:leftx\n";
+
+// units per second
+const PLAYER_SPEED: f32 = 3.;
+
+// markers for the entities the dropper's systems act on
+struct Player;
+struct Cube;
+struct Ground;
+struct Spotlight;
+
+// the dropper's state, kept in the world as resources
+struct DropTimer {
+    counter: i32,
+    time_since_drop: f32,
+}
 
-pub struct Dropper {
-    pub(crate) objects: HashMap<String, Object>, // <Object name, Object>
-    pub(crate) projection_matrix: Mat4,
-    pub(crate) view_matrix: Mat4,
-    pub(crate) screen_width: f32,
-    pub(crate) screen_height: f32,
-    pub(crate) counter: i32,
-    pub(crate) time_since_drop: f32,
+struct CubeAssets {
+    mesh: MeshHandle,
+    material: MaterialHandle,
 }
 
 impl Game for Dropper {
-    fn initialize(&mut self) {
+    fn initialize(&mut self, world: &mut World, schedule: &mut Schedule, assets: &mut Assets) {
         // Initialize the game
 
-        let plane_vertices: Vec<Vec3> = vec! [
-            Vec3::new(-1., 0., 1.),
-            Vec3::new(-1., 0., -1.),
-            Vec3::new(1., 0., -1.),
-            Vec3::new(1., 0.,  1.),
-        ];
-
-        let plane_indices: Vec<u32> = vec! [
-            0, 1, 2,
-            2, 3, 0,
-        ];
-
-        let cube_vertices: Vec<Vec3> = vec! [
-            // front face
-            Vec3::new(-0.5, -0.5, -0.5),
-            Vec3::new(-0.5, 0.5, -0.5),
-            Vec3::new(0.5, 0.5, -0.5),
-            Vec3::new(0.5, -0.5, -0.5),
-
-            // back face
-            Vec3::new(0.5, -0.5, 0.5),
-            Vec3::new(0.5, 0.5, 0.5),
-            Vec3::new(-0.5, 0.5, 0.5),
-            Vec3::new(-0.5, -0.5, 0.5),
-        ];
-
-        let cube_indices: Vec<u32> = vec! [
-            // front face
-            0, 1, 2,
-            2, 3, 0,
-
-            // back face
-            4, 5, 6,
-            6, 7, 4,
+        let player_material = assets.add_material(Material::colored(ProgramHandle::default(), Vec3::new(0., 1., 0.)));
+        let ground_material = assets.add_material(Material::colored(ProgramHandle::default(), Vec3::new(0., 0., 1.)));
+        let cube_material = assets.add_material(Material::colored(ProgramHandle::default(), Vec3::new(1., 0., 0.)));
 
-            // top face
-            1, 6, 5,
-            5, 2, 1,
+        let cube_mesh = assets.add_mesh("cube", || meshgen::cube(Vec3::ONE, 1));
+        let plane_mesh = assets.add_mesh("plane", || meshgen::plane(2., 2.));
 
-            // bottom face
-            7, 0, 3,
-            3, 4, 7,
+        let player = world.spawn();
+        world.insert(player, Transform::from_translation(Vec3::new(0., -2., 0.)));
+        world.insert(player, Collider::from_mesh(&cube_mesh));
+        world.insert(player, cube_mesh.clone());
+        world.insert(player, player_material);
+        world.insert(player, Player);
 
-            // right face
-            3, 2, 5,
-            5, 4, 3,
+        let ground = world.spawn();
+        world.insert(ground, Transform::from_translation_scale(Vec3::new(0., -3., 0.), Vec3::new(5., 1., 5.)));
+        world.insert(ground, Collider::from_mesh(&plane_mesh));
+        world.insert(ground, plane_mesh);
+        world.insert(ground, ground_material);
+        world.insert(ground, Shadows { cast: false, receive: true });
+        world.insert(ground, Ground);
 
-            // left face
-            7, 6, 1,
-            1, 0, 7,
-        ];
+        world.insert_resource(CubeAssets { mesh: cube_mesh, material: cube_material });
+        world.insert_resource(DropTimer { counter: 0, time_since_drop: 0. });
 
-        self.objects.insert("player".to_string(), Object::new(&cube_vertices, &cube_indices, Vec3::new(0.,0.,0.), Vec3::new(0., 1., 0.)));
+        // sun slightly in front of and to the left of the scene, covering the ground and drop zone
+        let sun = world.spawn();
+        world.insert(sun, Light::directional(Vec3::new(0.3, -1., -0.4), Vec3::new(0., 0., 0.), 8.));
 
-        self.objects.insert("ground".to_string(), Object::new(&plane_vertices, &plane_indices, Vec3::new(0.,0.,0.), Vec3::new(0., 0., 1.)));
-
-        // Translate player
-        self.objects.get_mut("player").unwrap().set_model_matrix(Mat4::from_translation(Vec3::new(0., -2., 0.)));
-
-        // Scale and translate ground
-        self.objects.get_mut("ground").unwrap().set_model_matrix(Mat4::from_scale_rotation_translation(Vec3::new(5., 1., 5.), Quat::IDENTITY, Vec3::new(0., -3., 0.)));
+        // spotlight following the player from above
+        let spotlight = world.spawn();
+        world.insert(spotlight, Spotlight);
 
         let l = -10.;
         let r = 10.;
         let b = -10.;
         let t = 10.;
-        let n = 0.1;
+        let n: f32 = 0.1;
         let aspect_ratio = (r - l) / (t - b);
-        let fov_y=(2.0 * ((t - b) / (2.0 * n)) as f32).atan();
+        let fov_y = (2.0 * ((t - b) / (2.0 * n))).atan();
 
-        self.projection_matrix = Mat4::perspective_infinite_rh(fov_y, aspect_ratio, n);
+        let projection_matrix = Mat4::perspective_infinite_rh(fov_y, aspect_ratio, n);
 
         let eye_x = 0.0;
         let eye_y = 0.0;
@@ -102,206 +101,101 @@ impl Game for Dropper {
         let up_y = 1.;
         let up_z = 0.;
 
-        self.view_matrix = Mat4::look_at_rh(Vec3::new(eye_x, eye_y, eye_z), Vec3::new(target_x, target_y, target_z), Vec3::new(up_x, up_y, up_z));
+        let view_matrix = Mat4::look_at_rh(Vec3::new(eye_x, eye_y, eye_z), Vec3::new(target_x, target_y, target_z), Vec3::new(up_x, up_y, up_z));
+        world.insert_resource(Camera { projection_matrix, view_matrix });
+
+        let path = Path::new("input.cfg");
+        let input = if path.exists() {
+            InputMap::load(path).unwrap_or_else(|e| {
+                println!("Couldn't load input bindings, using the defaults: {}", e);
+                InputMap::parse(DEFAULT_BINDINGS, "default bindings").unwrap()
+            })
+        } else {
+            InputMap::parse(DEFAULT_BINDINGS, "default bindings").unwrap()
+        };
+        world.resource_or_default::<InputState>().set_map(input);
+
+        schedule.add_system(Stage::Update, "move player", move_player);
+        schedule.add_system(Stage::Update, "drop cubes", drop_cubes);
+        schedule.add_system(Stage::Late, "remove collided cubes", remove_collided_cubes);
+        schedule.add_system(Stage::Late, "follow player with spotlight", follow_player_with_spotlight);
     }
 
-    fn update(&mut self, _delta_time: f32) {
-        // Update the game
-
-        // Apply downward motion to all cubes
-        for (key, object) in self.objects.iter_mut() {
-            if key != "player" && key != "ground" {
-                let mut object_center = object.get_center();
-                object_center[1] -= 0.01;
-                object.set_model_matrix(Mat4::from_scale_rotation_translation(Vec3::new(0.25, 0.25, 0.25), Quat::IDENTITY, object_center));
-
-            }
-        }
-
-        self.time_since_drop += _delta_time;
-        if self.time_since_drop > 1.0 {
-            self.counter += 1;
-            self.time_since_drop = 0.0;
-
-            // Drop a new cube
-            let cube_vertices: Vec<Vec3> = vec! [
-                // front face
-                Vec3::new(-0.5, -0.5, -0.5),
-                Vec3::new(-0.5, 0.5, -0.5),
-                Vec3::new(0.5, 0.5, -0.5),
-                Vec3::new(0.5, -0.5, -0.5),
-
-                // back face
-                Vec3::new(0.5, -0.5, 0.5),
-                Vec3::new(0.5, 0.5, 0.5),
-                Vec3::new(-0.5, 0.5, 0.5),
-                Vec3::new(-0.5, -0.5, 0.5),
-            ];
-
-            let cube_indices: Vec<u32> = vec! [
-                // front face
-                0, 1, 2,
-                2, 3, 0,
-
-                // back face
-                4, 5, 6,
-                6, 7, 4,
-
-                // top face
-                1, 6, 5,
-                5, 2, 1,
-
-                // bottom face
-                7, 0, 3,
-                3, 4, 7,
-
-                // right face
-                3, 2, 5,
-                5, 4, 3,
-
-                // left face
-                7, 6, 1,
-                1, 0, 7,
-            ];
-
-            let mut new_cube = Object::new(&cube_vertices, &cube_indices, Vec3::new(0., 0., 0.), Vec3::new(1., 0., 0.));
-            // Scale and translate new cube
-            let mut rng = rand::rng();
-            let x = rng.random_range(-4.5..4.5);
-            new_cube.set_model_matrix(Mat4::from_scale_rotation_translation(Vec3::new(0.25, 0.25, 0.25), Quat::IDENTITY, Vec3::new(x, 5., 0.)));
-            let cube_key = format!("cube_{}", self.counter);
-            self.objects.insert(cube_key, new_cube);
-        }
-
-        let player = self.objects.get_mut("player");
-        if player.is_some() {
-            let player = player.unwrap();
-            player.set_model_matrix(Mat4::from_translation(player.get_center()));
-        }
-            
-    }
+    fn handle_event(&mut self, _world: &mut World, _event: sdl2::event::Event) {}
+}
 
-    fn get_objects(&self) -> Vec<(String, &Object)> {
-            self.objects.iter()
-                .map(|(key, object)| (key.clone(), object))
-                .collect()
-        }
+// the first entity with marker component T
+fn find<T: 'static>(world: &World) -> Option<Entity> {
+    world.query::<T>().next().map(|(entity, _)| entity)
+}
 
-    fn get_projection_matrix(&self) -> glam::Mat4 {
-        self.projection_matrix
+fn move_player(world: &mut World) {
+    let delta_time = world.resource::<Time>().map_or(0., |time| time.delta);
+    let move_x = world.resource::<InputState>().map_or(0., |input| input.axis("move_x"));
+    let Some(player) = find::<Player>(world) else { return };
+    if move_x != 0. {
+        world.get_mut::<Transform>(player).unwrap().translate(Vec3::new(move_x * PLAYER_SPEED * delta_time, 0., 0.));
     }
+}
 
-    fn get_view_matrix(&self) -> glam::Mat4 {
-        self.view_matrix
+// drops a new cube every second, sharing the mesh created in initialize
+fn drop_cubes(world: &mut World) {
+    let delta_time = world.resource::<Time>().map_or(0., |time| time.delta);
+    let timer = world.resource_mut::<DropTimer>().unwrap();
+    timer.time_since_drop += delta_time;
+    if timer.time_since_drop <= 1.0 {
+        return;
     }
+    timer.counter += 1;
+    timer.time_since_drop = 0.0;
+
+    let cube_assets = world.resource::<CubeAssets>().unwrap();
+    let (mesh, material) = (cube_assets.mesh.clone(), cube_assets.material);
+
+    let x = world.resource_mut::<Random>().unwrap().gameplay().random_range(-4.5..4.5);
+    world.commands().spawn()
+        .insert(Transform::from_translation_scale(Vec3::new(x, 5., 0.), Vec3::splat(0.25)))
+        .insert(RigidBody { velocity: Vec3::new(0., -0.6, 0.), ..Default::default() })
+        .insert(Collider::from_mesh(&mesh))
+        .insert(mesh)
+        .insert(material)
+        .insert(Cube);
+}
 
-    fn handle_event(&mut self, event: sdl2::event::Event) {
-        // Handle user input events
-        let player = self.objects.get_mut("player");
-        if player.is_none() {
-            return;
-        }
-        let player = player.unwrap();
-        let mut player_center = player.get_center();
-
-        match event {
-            // Event::MouseButtonDown { x, y, mouse_btn, .. } => {
-            //     match mouse_btn {
-            //         sdl2::mouse::MouseButton::Left => {
-            //             // normalize x and y coordinates
-            //             let norm = Vec4::new(
-            //                 (2. * x as f32) / self.screen_width as f32 - 1.,
-            //                 1. - (2. * y as f32) / self.screen_height as f32,
-            //                 -1.,
-            //                 1.
-            //             );
-
-            //             // inverse projection matrix
-            //             let inverse_projection = self.projection_matrix.inverse();
-
-            //             // multiply inverse projection matrix and (x_n, y_n, z, w)
-            //             let mut ray_eye = inverse_projection * norm;
-            //             ray_eye[2] = -1.;
-            //             ray_eye[3] = 0.;
-
-            //             // inverse view matrix
-            //             let inverse_view = self.view_matrix.inverse();
-
-            //             // get camera position
-            //             let eye_position = inverse_view.col(3).truncate(); // Extract the translation (x, y, z)
-            //             let eye_x = eye_position.x;
-            //             let eye_y = eye_position.y;
-            //             let eye_z = eye_position.z;
-
-            //             // multiply inverse view matrix and ray_eye
-            //             let ray_world = inverse_view * ray_eye;
-
-            //             // normalize ray_world
-            //             let ray_direction = Vec3::new(ray_world[0], ray_world[1], ray_world[2]).normalize();
-
-            //             // use current z value for cube
-            //             let cube_z = player_center[2];
-
-            //             // calculate the intersection point at the cube's z value
-            //             let t = (cube_z - eye_z) / ray_direction[2];
-            //             let world_x = eye_x + t * ray_direction[0];
-            //             let world_y = eye_y + t * ray_direction[1];
-
-            //             player_center = Vec3::new(world_x, world_y, cube_z);
-
-            //             player.set_model_matrix(Mat4::from_translation(player_center));
-            //         },
-            //         _ => { }
-            //     }
-            // },
-            Event::KeyDown { keycode, .. } => {
-                match keycode {
-                    Some(key) => {
-                        match key {
-                            // player movement
-                            sdl2::keyboard::Keycode::A => {
-                                player_center[0] -= 0.1;
-                                player.set_model_matrix(Mat4::from_translation(player_center)); 
-                            },
-                            sdl2::keyboard::Keycode::D => { 
-                                player_center[0] += 0.1;
-                                player.set_model_matrix(Mat4::from_translation(player_center)); 
-                            },
-                            _ => { }
-                        }
-                    },
-                    None => { }
-                }
-            }
-            _ => { },            
-        }
-    }
-    fn handle_collisions(&mut self, _collisions: Vec<(String, String)>) {
-        for (object1, object2) in _collisions {
-            // Remove cubes that collide with the ground
-            if object1.contains("cube") && object2 == "ground" {
-                let cube = self.objects.get_mut(&object1).unwrap();
-                cube.set_model_matrix(Mat4::from_translation(cube.get_center()));
-                self.objects.remove(&object1);
+// cubes disappear when they hit the ground, and take the player with them when they hit it
+// an entity can be in several pairs, so removals are deferred until every pair is checked
+fn remove_collided_cubes(world: &mut World) {
+    let Some(collisions) = world.resource::<Collisions>() else { return };
+    let commands = world.commands();
+    for &(a, b) in &collisions.0 {
+        for (cube, other) in [(a, b), (b, a)] {
+            if !world.has::<Cube>(cube) {
+                continue;
             }
-            else if object2.contains("cube") && object1 == "ground" {
-                let cube = self.objects.get_mut(&object2).unwrap();
-                cube.set_model_matrix(Mat4::from_translation(cube.get_center()));
-                self.objects.remove(&object2);
-            }
-            // Remove cubes and player that collide with each other
-            else if object1.contains("cube") && object2 == "player" {
-                let cube = self.objects.get_mut(&object1).unwrap();
-                cube.set_model_matrix(Mat4::from_translation(cube.get_center()));
-                self.objects.remove(&object1);
-                self.objects.remove(&object2);
-            }
-            else if object2.contains("cube") && object1 == "player" {
-                let cube = self.objects.get_mut(&object2).unwrap();
-                cube.set_model_matrix(Mat4::from_translation(cube.get_center()));
-                self.objects.remove(&object2);
-                self.objects.remove(&object1);
+            if world.has::<Ground>(other) {
+                commands.despawn(cube);
+            } else if world.has::<Player>(other) {
+                commands.despawn(cube);
+                commands.despawn(other);
+                commands.add(|world| {
+                    if let Some(gamepads) = world.resource_mut::<Gamepads>() {
+                        gamepads.rumble_all(0.8, 0.4, Duration::from_millis(300));
+                    }
+                });
             }
         }
     }
-}
\ No newline at end of file
+}
+
+fn follow_player_with_spotlight(world: &mut World) {
+    let Some(spotlight) = find::<Spotlight>(world) else { return };
+    match find::<Player>(world) {
+        Some(player) => {
+            let position = world.world_matrix(player).w_axis.truncate() + Vec3::new(0., 4., 1.);
+            world.insert(spotlight, Light::spot(position, Vec3::new(0., -1., -0.25), 0.5, 10.));
+        },
+        None => {
+            world.remove::<Light>(spotlight);
+        },
+    }
+}
@@ -0,0 +1,295 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::commands::Commands;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+// an id for a set of components, the generation keeps ids of despawned entities from matching reused slots
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+// components of one type, packed densely for iteration with a sparse index for lookup by entity
+pub struct SparseSet<T> {
+    sparse: Vec<Option<usize>>, // dense index by entity index
+    entities: Vec<Entity>,
+    data: Vec<T>,
+    changed: Vec<bool>, // set when a component is inserted or borrowed mutably
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        SparseSet {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            data: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    fn dense(&self, entity: Entity) -> Option<usize> {
+        let dense = (*self.sparse.get(entity.index as usize)?)?;
+        (self.entities[dense] == entity).then_some(dense)
+    }
+
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        if let Some(dense) = self.dense(entity) {
+            self.data[dense] = component;
+            self.changed[dense] = true;
+            return;
+        }
+        let index = entity.index as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.data.len());
+        self.entities.push(entity);
+        self.data.push(component);
+        self.changed.push(true);
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let dense = self.dense(entity)?;
+        self.sparse[entity.index as usize] = None;
+        // the last component moves into the removed one's place
+        if let Some(moved) = self.entities.last().copied().filter(|last| *last != entity) {
+            self.sparse[moved.index as usize] = Some(dense);
+        }
+        self.entities.swap_remove(dense);
+        self.changed.swap_remove(dense);
+        Some(self.data.swap_remove(dense))
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense(entity).map(|dense| &self.data[dense])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        let dense = self.dense(entity)?;
+        self.changed[dense] = true;
+        Some(&mut self.data[dense])
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense(entity).is_some()
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(&self.data)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.iter_flagged().map(|(entity, component, changed)| {
+            *changed = true;
+            (entity, component)
+        })
+    }
+
+    // components with their changed flags, for queries that only mark the components they yield
+    fn iter_flagged(&mut self) -> impl Iterator<Item = (Entity, &mut T, &mut bool)> {
+        self.entities.iter().copied().zip(self.data.iter_mut().zip(self.changed.iter_mut()))
+            .map(|(entity, (component, changed))| (entity, component, changed))
+    }
+
+    // whether the entity's component was inserted or borrowed mutably since the last clear_changed
+    pub fn is_changed(&self, entity: Entity) -> bool {
+        self.dense(entity).is_some_and(|dense| self.changed[dense])
+    }
+
+    pub fn clear_changed(&mut self) {
+        self.changed.fill(false);
+    }
+}
+
+// lets the world remove a despawned entity's components without knowing their types
+trait Storage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> Storage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// entities with components of any 'static type, and resources that exist once per world
+// queries iterate the first component's storage and look the others up, so put the rarest component first
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    storages: HashMap<TypeId, Box<dyn Storage>>,
+    resources: HashMap<TypeId, Box<dyn Any>>,
+    commands: Commands,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                self.generations.len() as u32 - 1
+            },
+        };
+        self.alive[index as usize] = true;
+        Entity { index, generation: self.generations[index as usize] }
+    }
+
+    // removes the entity and its components, returning whether it was alive
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    // the queue for changes that can't be made while the world is borrowed
+    pub fn commands(&self) -> &Commands {
+        &self.commands
+    }
+
+    pub fn apply_commands(&mut self) {
+        Commands::apply(self);
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index) == Some(&true) && self.generations[index] == entity.generation
+    }
+
+    #[allow(dead_code)]
+    pub fn entity_count(&self) -> usize {
+        self.alive.iter().filter(|alive| **alive).count()
+    }
+
+    pub fn storage<T: 'static>(&self) -> Option<&SparseSet<T>> {
+        self.storages.get(&TypeId::of::<T>()).and_then(|storage| storage.as_any().downcast_ref())
+    }
+
+    pub fn storage_mut<T: 'static>(&mut self) -> &mut SparseSet<T> {
+        self.storages.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::default()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    // adds or replaces the entity's component of type T, does nothing if the entity was despawned
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) {
+        if self.is_alive(entity) {
+            self.storage_mut().insert(entity, component);
+        }
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storages.get_mut(&TypeId::of::<T>())?.as_any_mut().downcast_mut::<SparseSet<T>>()?.remove(entity)
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.storage()?.get(entity)
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storages.get_mut(&TypeId::of::<T>())?.as_any_mut().downcast_mut::<SparseSet<T>>()?.get_mut(entity)
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.storage::<T>().is_some_and(|storage| storage.contains(entity))
+    }
+
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.storage::<T>().into_iter().flat_map(SparseSet::iter)
+    }
+
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.storage_mut::<T>().iter_mut()
+    }
+
+    // entities with both an A and a B
+    pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        let b = self.storage::<B>();
+        self.query::<A>().filter_map(move |(entity, a)| Some((entity, a, b?.get(entity)?)))
+    }
+
+    // like query2, with A borrowed mutably, A and B must be different types
+    pub fn query2_mut<A: 'static, B: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut A, &B)> {
+        self.storage_mut::<A>();
+        self.storage_mut::<B>();
+        let [a, b] = self.storages.get_disjoint_mut([&TypeId::of::<A>(), &TypeId::of::<B>()]);
+        let a = a.unwrap().as_any_mut().downcast_mut::<SparseSet<A>>().unwrap();
+        let b = b.unwrap().as_any().downcast_ref::<SparseSet<B>>().unwrap();
+        a.iter_flagged().filter_map(move |(entity, a, changed)| {
+            let b = b.get(entity)?;
+            *changed = true;
+            Some((entity, a, b))
+        })
+    }
+
+    // entities with an A, a B and a C
+    pub fn query3<A: 'static, B: 'static, C: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B, &C)> {
+        let c = self.storage::<C>();
+        self.query2::<A, B>().filter_map(move |(entity, a, b)| Some((entity, a, b, c?.get(entity)?)))
+    }
+
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), Box::new(resource));
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources.remove(&TypeId::of::<T>()).and_then(|resource| resource.downcast().ok()).map(|resource| *resource)
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.resources.get(&TypeId::of::<T>()).and_then(|resource| resource.downcast_ref())
+    }
+
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut(&TypeId::of::<T>()).and_then(|resource| resource.downcast_mut())
+    }
+
+    // the resource, inserting T::default() first if there isn't one
+    pub fn resource_or_default<T: Default + 'static>(&mut self) -> &mut T {
+        self.resources.entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut()
+            .unwrap()
+    }
+}
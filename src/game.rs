@@ -1,11 +1,11 @@
-use crate::object::Object;
+use crate::assets::Assets;
+use crate::ecs::World;
+use crate::schedule::Schedule;
 
+// game state lives in the world as components and resources, and changes through the game's systems
+// the frame is rendered from the Camera resource, the Light components and every entity with a mesh and material
 pub trait Game {
-    fn initialize(&mut self);
-    fn update(&mut self, delta_time: f32);
-    fn get_objects(&self) -> Vec<(String, &Object)>;
-    fn get_projection_matrix(&self) -> glam::Mat4;
-    fn get_view_matrix(&self) -> glam::Mat4;
-    fn handle_event(&mut self, event: sdl2::event::Event);
-    fn handle_collisions(&mut self, collisions: Vec<(String, String)>); // <Object name, Object name>
-}
\ No newline at end of file
+    // spawns the starting entities and adds the game's systems to `schedule`
+    fn initialize(&mut self, world: &mut World, schedule: &mut Schedule, assets: &mut Assets);
+    fn handle_event(&mut self, world: &mut World, event: sdl2::event::Event);
+}
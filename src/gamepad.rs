@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use glam::Vec2;
+use sdl2::controller::{Axis, GameController};
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    pub fn axes(self) -> (Axis, Axis) {
+        match self {
+            Stick::Left => (Axis::LeftX, Axis::LeftY),
+            Stick::Right => (Axis::RightX, Axis::RightY),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Left,
+    Right,
+}
+
+impl Trigger {
+    pub fn axis(self) -> Axis {
+        match self {
+            Trigger::Left => Axis::TriggerLeft,
+            Trigger::Right => Axis::TriggerRight,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// how far sticks and triggers move before they register, worn pads rarely rest at exactly 0
+pub struct DeadZones {
+    pub stick: f32,   // radius, from 0 to 1
+    pub trigger: f32, // from 0 to 1
+}
+
+impl Default for DeadZones {
+    fn default() -> Self {
+        DeadZones { stick: 0.15, trigger: 0.05 }
+    }
+}
+
+// a stick position with the dead zone removed and the rest rescaled, so values still reach 1 at the edge
+// the dead zone is circular, so diagonals aren't snapped to the axes
+pub fn stick_dead_zone(position: Vec2, dead_zone: f32) -> Vec2 {
+    let length = position.length();
+    if length <= dead_zone {
+        return Vec2::ZERO;
+    }
+    position / length * ((length - dead_zone) / (1. - dead_zone)).min(1.)
+}
+
+pub fn trigger_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value <= dead_zone {
+        return 0.;
+    }
+    ((value - dead_zone) / (1. - dead_zone)).min(1.)
+}
+
+// a connected controller, its buttons and axes are read through InputState
+pub struct Gamepad {
+    controller: GameController,
+}
+
+impl Gamepad {
+    // matches the `which` of its controller events and InputState's pad ids
+    pub fn id(&self) -> u32 {
+        self.controller.instance_id()
+    }
+
+    pub fn name(&self) -> String {
+        self.controller.name()
+    }
+
+    pub fn has_rumble(&self) -> bool {
+        self.controller.has_rumble()
+    }
+
+    // shakes the pad's low and high frequency motors at strengths from 0 to 1, replacing any rumble still playing
+    pub fn rumble(&mut self, low: f32, high: f32, duration: Duration) -> Result<(), String> {
+        let strength = |value: f32| (value.clamp(0., 1.) * u16::MAX as f32) as u16;
+        self.controller.set_rumble(strength(low), strength(high), duration.as_millis().min(u32::MAX as u128) as u32)
+            .map_err(|e| format!("Couldn't rumble {}: {}", self.name(), e))
+    }
+}
+
+// the connected controllers, opened and closed as they're plugged in and out
+// SDL reports controllers that were connected at startup as added, so they're opened the same way
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Gamepad>,
+}
+
+impl Gamepads {
+    pub fn new(subsystem: GameControllerSubsystem) -> Self {
+        Gamepads { subsystem, pads: Vec::new() }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => match self.subsystem.open(which) {
+                Ok(controller) => {
+                    if self.get(controller.instance_id()).is_none() {
+                        println!("Connected {}", controller.name());
+                        self.pads.push(Gamepad { controller });
+                    }
+                },
+                Err(e) => println!("Couldn't open controller {}: {}", which, e),
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(index) = self.pads.iter().position(|pad| pad.id() == which) {
+                    println!("Disconnected {}", self.pads[index].name());
+                    self.pads.remove(index);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // in the order they were connected
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = &Gamepad> {
+        self.pads.iter()
+    }
+
+    #[allow(dead_code)]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Gamepad> {
+        self.pads.iter_mut()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Gamepad> {
+        self.pads.iter().find(|pad| pad.id() == id)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Gamepad> {
+        self.pads.iter_mut().find(|pad| pad.id() == id)
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.pads.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.pads.is_empty()
+    }
+
+    // rumbles every pad that can, for feedback that isn't aimed at one player
+    pub fn rumble_all(&mut self, low: f32, high: f32, duration: Duration) {
+        for pad in self.pads.iter_mut().filter(|pad| pad.has_rumble()) {
+            if let Err(e) = pad.rumble(low, high, duration) {
+                println!("{}", e);
+            }
+        }
+    }
+}
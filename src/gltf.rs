@@ -0,0 +1,623 @@
+use std::{collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}, rc::Rc};
+
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+use crate::animation::{AnimationClip, Channel, Interpolation, Joint, Keyframes, Skeleton, MAX_JOINTS};
+use crate::assets::{Assets, ProgramHandle};
+use crate::json::Json;
+use crate::material::{BlendMode, CullMode, Material, MaterialHandle, MaterialParam};
+use crate::mesh::{MeshData, MeshHandle};
+use crate::model::{Model, ModelNode, ModelPart};
+use crate::transform::Transform;
+
+// gltf 2.0 scenes, as .gltf json with external or embedded buffers, or binary .glb
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+
+// accessor component types
+const BYTE: usize = 5120;
+const UNSIGNED_BYTE: usize = 5121;
+const SHORT: usize = 5122;
+const UNSIGNED_SHORT: usize = 5123;
+const UNSIGNED_INT: usize = 5125;
+const FLOAT: usize = 5126;
+
+const TRIANGLES: usize = 4;
+
+fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(format!("invalid base64 character '{}'", c as char)),
+        };
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// splits a .glb file into its json and binary chunks
+fn parse_glb(bytes: &[u8]) -> Result<(String, Option<Vec<u8>>), String> {
+    if read_u32(bytes, 4) != Some(2) {
+        return Err("only glb version 2 is supported".to_string());
+    }
+    let length = (read_u32(bytes, 8).unwrap_or(0) as usize).min(bytes.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(bytes, offset).unwrap_or(0) as usize;
+        let chunk_type = read_u32(bytes, offset + 4).unwrap_or(0);
+        let data = bytes.get(offset + 8..offset + 8 + chunk_length).ok_or("glb chunk extends past the end of the file")?;
+        match chunk_type {
+            GLB_JSON_CHUNK => json = Some(String::from_utf8(data.to_vec()).map_err(|_| "glb json chunk isn't utf-8")?),
+            GLB_BIN_CHUNK => bin = Some(data.to_vec()),
+            _ => {}, // unknown chunks must be ignored
+        }
+        offset += 8 + chunk_length.div_ceil(4) * 4;
+    }
+    Ok((json.ok_or("glb has no json chunk")?, bin))
+}
+
+// a typed view of elements in a buffer
+struct Accessor<'a> {
+    bytes: Option<&'a [u8]>, // None if every element is zero
+    offset: usize,
+    stride: usize,
+    count: usize,
+    components: usize,
+    component_type: usize,
+    normalized: bool,
+}
+
+impl Accessor<'_> {
+    fn component(&self, element: usize, component: usize) -> Option<&[u8]> {
+        let size = match self.component_type {
+            BYTE | UNSIGNED_BYTE => 1,
+            SHORT | UNSIGNED_SHORT => 2,
+            _ => 4,
+        };
+        let at = self.offset + element * self.stride + component * size;
+        self.bytes.map(|bytes| &bytes[at..at + size])
+    }
+
+    // a component as stored, for integer accessors like indices
+    fn integer(&self, element: usize, component: usize) -> u32 {
+        let Some(b) = self.component(element, component) else { return 0 };
+        match b.len() {
+            1 => b[0] as u32,
+            2 => u16::from_le_bytes([b[0], b[1]]) as u32,
+            _ => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        }
+    }
+
+    // a component as a float, mapping normalized integers to [0, 1] or [-1, 1]
+    fn float(&self, element: usize, component: usize) -> f32 {
+        let Some(b) = self.component(element, component) else { return 0. };
+        match self.component_type {
+            BYTE if self.normalized => (b[0] as i8 as f32 / 127.).max(-1.),
+            BYTE => b[0] as i8 as f32,
+            UNSIGNED_BYTE if self.normalized => b[0] as f32 / 255.,
+            SHORT if self.normalized => (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.).max(-1.),
+            SHORT => i16::from_le_bytes([b[0], b[1]]) as f32,
+            UNSIGNED_SHORT if self.normalized => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.,
+            FLOAT => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            _ => self.integer(element, component) as f32,
+        }
+    }
+}
+
+// the parsed document and its loaded buffers
+struct Gltf {
+    json: Json,
+    buffers: Vec<Vec<u8>>,
+    file: String,
+    dir: PathBuf,
+}
+
+impl Gltf {
+    fn error(&self, message: impl std::fmt::Display) -> String {
+        format!("{}: {}", self.file, message)
+    }
+
+    // the top-level array `key`, empty if the document has none
+    fn array(&self, key: &str) -> &[Json] {
+        self.json.get(key).and_then(Json::as_array).unwrap_or(&[])
+    }
+
+    fn element(&self, key: &str, index: usize) -> Result<&Json, String> {
+        self.array(key).get(index).ok_or_else(|| self.error(format!("{} {} doesn't exist", key, index)))
+    }
+
+    // the contents of a data uri, or of a file relative to the document
+    fn load_uri(&self, uri: &str) -> Result<Vec<u8>, String> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (_, encoded) = data.split_once(";base64,").ok_or_else(|| self.error("only base64 data uris are supported"))?;
+            return base64_decode(encoded).map_err(|e| self.error(e));
+        }
+        let path = self.dir.join(uri.replace("%20", " "));
+        fs::read(&path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))
+    }
+
+    // the bytes of a buffer view and the stride between its elements, if it interleaves them
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), String> {
+        let view = self.element("bufferViews", index)?;
+        let buffer = view.get("buffer").and_then(Json::as_usize).ok_or_else(|| self.error(format!("buffer view {} has no buffer", index)))?;
+        let offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let length = view.get("byteLength").and_then(Json::as_usize).unwrap_or(0);
+        let bytes = self.buffers.get(buffer)
+            .zip(offset.checked_add(length))
+            .and_then(|(buffer, end)| buffer.get(offset..end))
+            .ok_or_else(|| self.error(format!("buffer view {} is out of range of buffer {}", index, buffer)))?;
+        Ok((bytes, view.get("byteStride").and_then(Json::as_usize)))
+    }
+
+    // where the elements of accessor `index` are and how they're stored
+    // `expected` is the element count the caller needs, if it knows one, and is what bounds accessors without a buffer view
+    fn accessor(&self, index: usize, expected: Option<usize>) -> Result<Accessor<'_>, String> {
+        let accessor = self.element("accessors", index)?;
+        if accessor.get("sparse").is_some() {
+            return Err(self.error(format!("accessor {} is sparse, which isn't supported", index)));
+        }
+
+        let count = accessor.get("count").and_then(Json::as_usize).ok_or_else(|| self.error(format!("accessor {} has no count", index)))?;
+        if expected.is_some_and(|expected| count != expected) {
+            return Err(self.error(format!("accessor {} has {} elements, expected {}", index, count, expected.unwrap())));
+        }
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            other => return Err(self.error(format!("accessor {} has unsupported type {:?}", index, other))),
+        };
+        let component_type = accessor.get("componentType").and_then(Json::as_usize).unwrap_or(0);
+        let component_size = match component_type {
+            BYTE | UNSIGNED_BYTE => 1,
+            SHORT | UNSIGNED_SHORT => 2,
+            UNSIGNED_INT | FLOAT => 4,
+            _ => return Err(self.error(format!("accessor {} has unsupported component type {}", index, component_type))),
+        };
+        let normalized = accessor.get("normalized").and_then(Json::as_bool).unwrap_or(false);
+
+        // accessors without a buffer view are all zeros, only allowed where the count is known so a huge one isn't allocated
+        let element_size = components * component_size;
+        let Some(view) = accessor.get("bufferView").and_then(Json::as_usize) else {
+            if expected.is_none() {
+                return Err(self.error(format!("accessor {} has no buffer view", index)));
+            }
+            return Ok(Accessor { bytes: None, offset: 0, stride: element_size, count, components, component_type, normalized });
+        };
+        let (bytes, stride) = self.buffer_view(view)?;
+        let offset = accessor.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let stride = stride.unwrap_or(element_size);
+        // the values come from the file, so the end is computed without overflowing
+        let end = stride.checked_mul(count.saturating_sub(1)).and_then(|end| end.checked_add(offset)).and_then(|end| end.checked_add(element_size));
+        if count > 0 && end.is_none_or(|end| end > bytes.len()) {
+            return Err(self.error(format!("accessor {} reads past the end of buffer view {}", index, view)));
+        }
+        Ok(Accessor { bytes: Some(bytes), offset, stride, count, components, component_type, normalized })
+    }
+
+    // an accessor that must have `components` per element, and `count` elements if given, as elements
+    fn read_vectors(&self, index: usize, components: usize, count: Option<usize>, name: &str) -> Result<Vec<Vec<f32>>, String> {
+        let accessor = self.accessor(index, count)?;
+        if accessor.components != components {
+            return Err(self.error(format!("{} accessor {} has {} components, expected {}", name, index, accessor.components, components)));
+        }
+        Ok((0..accessor.count).map(|i| (0..components).map(|c| accessor.float(i, c)).collect()).collect())
+    }
+
+    fn read_joints(&self, index: usize, count: usize) -> Result<Vec<[u16; 4]>, String> {
+        let accessor = self.accessor(index, Some(count))?;
+        if !matches!(accessor.component_type, UNSIGNED_BYTE | UNSIGNED_SHORT) || accessor.components != 4 {
+            return Err(self.error(format!("JOINTS_0 accessor {} must have 4 unsigned byte or short components", index)));
+        }
+        let joints: Vec<[u16; 4]> = (0..accessor.count).map(|i| std::array::from_fn(|c| accessor.integer(i, c) as u16)).collect();
+        // the skinning shader only has room for MAX_JOINTS matrices
+        if let Some(joint) = joints.iter().flatten().find(|joint| **joint as usize >= MAX_JOINTS) {
+            return Err(self.error(format!("JOINTS_0 accessor {} uses joint {}, at most {} are supported", index, joint, MAX_JOINTS)));
+        }
+        Ok(joints)
+    }
+
+    fn read_indices(&self, index: usize) -> Result<Vec<u32>, String> {
+        let accessor = self.accessor(index, None)?;
+        if accessor.component_type == FLOAT || accessor.components != 1 {
+            return Err(self.error(format!("index accessor {} must have scalar integer components", index)));
+        }
+        Ok((0..accessor.count).map(|i| accessor.integer(i, 0)).collect())
+    }
+
+    fn read_primitive(&self, primitive: &Json, name: &str) -> Result<MeshData, String> {
+        let mode = primitive.get("mode").and_then(Json::as_usize).unwrap_or(TRIANGLES);
+        if mode != TRIANGLES {
+            return Err(self.error(format!("{} uses primitive mode {}, only triangles are supported", name, mode)));
+        }
+
+        let attributes = primitive.get("attributes");
+        let attribute = |key: &str| attributes.and_then(|attributes| attributes.get(key)).and_then(Json::as_usize);
+        let positions = attribute("POSITION").ok_or_else(|| self.error(format!("{} has no POSITION attribute", name)))?;
+        let positions = self.read_vectors(positions, 3, None, "POSITION")?;
+        // every other attribute has one element per position
+        let count = Some(positions.len());
+        let normals = attribute("NORMAL").map(|index| self.read_vectors(index, 3, count, "NORMAL")).transpose()?;
+        let uvs = attribute("TEXCOORD_0").map(|index| self.read_vectors(index, 2, count, "TEXCOORD_0")).transpose()?;
+        let joints = attribute("JOINTS_0").map(|index| self.read_joints(index, positions.len())).transpose()?;
+        let weights = attribute("WEIGHTS_0").map(|index| self.read_vectors(index, 4, count, "WEIGHTS_0")).transpose()?;
+        if joints.is_some() != weights.is_some() {
+            return Err(self.error(format!("{} needs both JOINTS_0 and WEIGHTS_0 to be skinned", name)));
+        }
+
+        let mut data = MeshData::new();
+        for (i, position) in positions.iter().enumerate() {
+            let normal = normals.as_ref().map_or(Vec3::ZERO, |normals| Vec3::from_slice(&normals[i]));
+            // gltf texture coordinates start at the top of the image, opengl's at the bottom
+            let uv = uvs.as_ref().map_or(Vec2::ZERO, |uvs| Vec2::new(uvs[i][0], 1. - uvs[i][1]));
+            data.push_vertex(Vec3::from_slice(position), normal, uv);
+
+            if let (Some(joints), Some(weights)) = (&joints, &weights) {
+                let vertex = data.vertices.last_mut().unwrap();
+                vertex.joints = joints[i];
+                let weights = Vec4::from_slice(&weights[i]);
+                let total = weights.element_sum();
+                vertex.weights = if total > 0. { weights / total } else { Vec4::X };
+            }
+        }
+
+        data.indices = match primitive.get("indices").and_then(Json::as_usize) {
+            Some(indices) => self.read_indices(indices)?,
+            None => (0..positions.len() as u32).collect(),
+        };
+        if !data.indices.len().is_multiple_of(3) {
+            return Err(self.error(format!("{} has {} indices, which isn't a whole number of triangles", name, data.indices.len())));
+        }
+        if let Some(index) = data.indices.iter().find(|index| **index as usize >= positions.len()) {
+            return Err(self.error(format!("{} indexes vertex {} of {}", name, index, positions.len())));
+        }
+
+        if normals.is_none() {
+            data.compute_normals(&vec![true; data.vertices.len()]);
+        }
+        Ok(data)
+    }
+
+    // the image's bytes and a name to cache its texture under
+    fn read_image(&self, index: usize) -> Result<(Vec<u8>, String), String> {
+        let image = self.element("images", index)?;
+        if let Some(uri) = image.get("uri").and_then(Json::as_str) {
+            let name = if uri.starts_with("data:") { format!("{}#image{}", self.file, index) } else { self.dir.join(uri).display().to_string() };
+            return Ok((self.load_uri(uri)?, name));
+        }
+        let view = image.get("bufferView").and_then(Json::as_usize).ok_or_else(|| self.error(format!("image {} has no uri or buffer view", index)))?;
+        Ok((self.buffer_view(view)?.0.to_vec(), format!("{}#image{}", self.file, index)))
+    }
+
+    // the engine material for gltf material `index`, or for the default material if there's none,
+    // and the base color alpha the object color carries
+    fn create_material(&self, assets: &mut Assets, index: Option<usize>, skinned: bool) -> Result<(MaterialHandle, f32), String> {
+        let material = match index {
+            Some(index) => self.element("materials", index)?,
+            None => &Json::Object(HashMap::new()),
+        };
+        let pbr = material.get("pbrMetallicRoughness");
+        let [r, g, b, a] = pbr.and_then(|pbr| pbr.get("baseColorFactor")).and_then(Json::as_f32_array::<4>).unwrap_or([1.; 4]);
+        let texture = pbr.and_then(|pbr| pbr.get("baseColorTexture")).and_then(|texture| texture.get("index")).and_then(Json::as_usize);
+
+        let mut defines = Vec::new();
+        if texture.is_some() {
+            defines.push("DIFFUSE_TEXTURE");
+        }
+        if skinned {
+            defines.push("SKINNING");
+        }
+        let program = if defines.is_empty() {
+            ProgramHandle::default()
+        } else {
+            assets.load_program("default.vert", "default.frag", &defines)?
+        };
+
+        let mut engine_material = match texture {
+            Some(texture) => {
+                let source = self.element("textures", texture)?.get("source").and_then(Json::as_usize)
+                    .ok_or_else(|| self.error(format!("texture {} has no source image", texture)))?;
+                let (bytes, name) = self.read_image(source)?;
+                let texture = assets.load_texture_from_memory(&name, &bytes)?;
+                let mut engine_material = Material::colored(program, Vec3::new(r, g, b));
+                engine_material.set("u_diffuse_texture", MaterialParam::Texture(texture));
+                engine_material
+            },
+            None => Material::colored(program, Vec3::new(r, g, b)),
+        };
+
+        // masked materials are drawn opaque, alpha testing isn't supported
+        if material.get("alphaMode").and_then(Json::as_str) == Some("BLEND") {
+            engine_material.blend_mode = BlendMode::Alpha;
+        }
+        if !material.get("doubleSided").and_then(Json::as_bool).unwrap_or(false) {
+            engine_material.cull_mode = CullMode::Back;
+        }
+        Ok((assets.add_material(engine_material), a))
+    }
+}
+
+impl Gltf {
+    // node names identify joints in animation clips, so every node needs one
+    fn node_name(&self, index: usize) -> String {
+        let name = self.array("nodes").get(index).and_then(|node| node.get("name")).and_then(Json::as_str);
+        name.map_or_else(|| format!("node{}", index), str::to_string)
+    }
+
+    // the skeleton of skin `index`, whose joints are nodes in the hierarchy described by `parents` and `globals`
+    fn create_skeleton(&self, index: usize, parents: &HashMap<usize, usize>, globals: &HashMap<usize, Mat4>) -> Result<Skeleton, String> {
+        let skin = self.element("skins", index)?;
+        let nodes: Vec<usize> = skin.get("joints").and_then(Json::as_array).unwrap_or(&[]).iter().filter_map(Json::as_usize).collect();
+        let inverse_binds = match skin.get("inverseBindMatrices").and_then(Json::as_usize) {
+            Some(accessor) => self.read_vectors(accessor, 16, Some(nodes.len()), "inverseBindMatrices")?.iter().map(|m| Mat4::from_cols_slice(m)).collect(),
+            None => vec![Mat4::IDENTITY; nodes.len()],
+        };
+
+        // joints are parented to the nearest ancestor that's also a joint
+        let joint_of = |node: usize| nodes.iter().position(|joint| *joint == node);
+        let mut root = None;
+        let mut joints = Vec::with_capacity(nodes.len());
+        for (i, &node) in nodes.iter().enumerate() {
+            let mut ancestor = parents.get(&node).copied();
+            while let Some(a) = ancestor.filter(|a| joint_of(*a).is_none()) {
+                ancestor = parents.get(&a).copied();
+            }
+            let parent = ancestor.and_then(joint_of);
+            if parent.is_none() && root.is_none() {
+                root = Some(parents.get(&node).and_then(|parent| globals.get(parent)).copied().unwrap_or(Mat4::IDENTITY));
+            }
+
+            joints.push(Joint {
+                name: self.node_name(node),
+                parent,
+                rest: Transform::from_matrix(&local_matrix(self.element("nodes", node)?)),
+                inverse_bind: inverse_binds[i],
+            });
+        }
+
+        Skeleton::new(joints, root.unwrap_or(Mat4::IDENTITY)).map_err(|e| self.error(format!("skin {}: {}", index, e)))
+    }
+
+    fn read_animation(&self, index: usize) -> Result<AnimationClip, String> {
+        let animation = self.element("animations", index)?;
+        let samplers = animation.get("samplers").and_then(Json::as_array).unwrap_or(&[]);
+        let mut channels = Vec::new();
+
+        for channel in animation.get("channels").and_then(Json::as_array).unwrap_or(&[]) {
+            let target = channel.get("target");
+            let Some(node) = target.and_then(|target| target.get("node")).and_then(Json::as_usize) else { continue };
+            let path = target.and_then(|target| target.get("path")).and_then(Json::as_str).unwrap_or("");
+            let components = match path {
+                "translation" | "scale" => 3,
+                "rotation" => 4,
+                _ => continue, // morph target weights aren't supported
+            };
+
+            let sampler = channel.get("sampler").and_then(Json::as_usize).and_then(|sampler| samplers.get(sampler))
+                .ok_or_else(|| self.error(format!("animation {} has a channel without a sampler", index)))?;
+            let input = sampler.get("input").and_then(Json::as_usize).ok_or_else(|| self.error(format!("animation {} has a sampler without input", index)))?;
+            let output = sampler.get("output").and_then(Json::as_usize).ok_or_else(|| self.error(format!("animation {} has a sampler without output", index)))?;
+            let times: Vec<f32> = self.read_vectors(input, 1, None, "animation input")?.into_iter().map(|time| time[0]).collect();
+
+            // cubic splines store an in tangent, value and out tangent per keyframe, the tangents are dropped
+            let interpolation = sampler.get("interpolation").and_then(Json::as_str);
+            let per_keyframe = if interpolation == Some("CUBICSPLINE") { 3 } else { 1 };
+            let values = self.read_vectors(output, components, Some(times.len() * per_keyframe), "animation output")?;
+            let values: Vec<Vec<f32>> = values.into_iter().skip(per_keyframe / 3).step_by(per_keyframe).collect();
+            let interpolation = if interpolation == Some("STEP") { Interpolation::Step } else { Interpolation::Linear };
+
+            let keyframes = match path {
+                "translation" => Keyframes::Translation(values.iter().map(|v| Vec3::from_slice(v)).collect()),
+                "scale" => Keyframes::Scale(values.iter().map(|v| Vec3::from_slice(v)).collect()),
+                _ => Keyframes::Rotation(values.iter().map(|v| Quat::from_slice(v).normalize()).collect()),
+            };
+            channels.push(Channel {
+                joint: self.node_name(node),
+                times,
+                keyframes,
+                interpolation,
+            });
+        }
+
+        let name = animation.get("name").and_then(Json::as_str).map_or_else(|| format!("animation{}", index), str::to_string);
+        Ok(AnimationClip::new(&name, channels))
+    }
+}
+
+fn local_matrix(node: &Json) -> Mat4 {
+    if let Some(matrix) = node.get("matrix").and_then(Json::as_f32_array::<16>) {
+        return Mat4::from_cols_array(&matrix);
+    }
+    let translation = node.get("translation").and_then(Json::as_f32_array::<3>).map_or(Vec3::ZERO, Vec3::from);
+    let rotation = node.get("rotation").and_then(Json::as_f32_array::<4>).map_or(Quat::IDENTITY, Quat::from_array);
+    let scale = node.get("scale").and_then(Json::as_f32_array::<3>).map_or(Vec3::ONE, Vec3::from);
+    Mat4::from_scale_rotation_translation(scale, rotation, translation)
+}
+
+// loads the default scene of a .gltf or .glb file into `assets`, with a node for every node in the scene
+// and a part for every mesh primitive, attached to its node and named <node>.<primitive index>
+// skinned parts get the skeleton of their skin, and every animation becomes a clip
+#[allow(dead_code)] // called by games, the dropper doesn't load models
+pub fn load(assets: &mut Assets, path: &Path) -> Result<Model, String> {
+    let file = path.display().to_string();
+    let bytes = fs::read(path).map_err(|e| format!("Couldn't read {}: {}", file, e))?;
+
+    let (source, bin) = if read_u32(&bytes, 0) == Some(GLB_MAGIC) {
+        parse_glb(&bytes).map_err(|e| format!("{}: {}", file, e))?
+    } else {
+        (String::from_utf8(bytes).map_err(|_| format!("{}: isn't utf-8 json or a glb file", file))?, None)
+    };
+    let json = Json::parse(&source).map_err(|e| format!("{}: {}", file, e))?;
+
+    let mut gltf = Gltf {
+        json,
+        buffers: Vec::new(),
+        file: file.clone(),
+        dir: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+    };
+
+    let version = gltf.json.get("asset").and_then(|asset| asset.get("version")).and_then(Json::as_str).unwrap_or("");
+    if !version.starts_with("2.") {
+        return Err(gltf.error(format!("unsupported gltf version \"{}\"", version)));
+    }
+
+    let mut bin = bin;
+    let mut buffers = Vec::new();
+    for (i, buffer) in gltf.array("buffers").iter().enumerate() {
+        match buffer.get("uri").and_then(Json::as_str) {
+            Some(uri) => buffers.push(gltf.load_uri(uri)?),
+            // the first buffer of a glb may refer to its binary chunk
+            None if i == 0 => buffers.push(bin.take().ok_or_else(|| gltf.error("buffer 0 has no uri and there's no glb binary chunk"))?),
+            None => return Err(gltf.error(format!("buffer {} has no uri", i))),
+        }
+    }
+    gltf.buffers = buffers;
+
+    // the default scene's root nodes, or every node that isn't a child if there are no scenes
+    let roots: Vec<usize> = match gltf.array("scenes").get(gltf.json.get("scene").and_then(Json::as_usize).unwrap_or(0)) {
+        Some(scene) => scene.get("nodes").and_then(Json::as_array).unwrap_or(&[]).iter().filter_map(Json::as_usize).collect(),
+        None => {
+            let children: HashSet<usize> = gltf.array("nodes").iter()
+                .flat_map(|node| node.get("children").and_then(Json::as_array).unwrap_or(&[]))
+                .filter_map(Json::as_usize)
+                .collect();
+            (0..gltf.array("nodes").len()).filter(|node| !children.contains(node)).collect()
+        },
+    };
+
+    // every node in the scene in depth-first order, so parents come before their children
+    let mut nodes: Vec<usize> = Vec::new();
+    let mut parents: HashMap<usize, usize> = HashMap::new();
+    let mut globals: HashMap<usize, Mat4> = HashMap::new();
+    let mut stack: Vec<(usize, Mat4)> = roots.into_iter().rev().map(|node| (node, Mat4::IDENTITY)).collect();
+    while let Some((index, parent)) = stack.pop() {
+        if globals.contains_key(&index) {
+            return Err(gltf.error(format!("node {} appears more than once in the hierarchy", index)));
+        }
+        let node = gltf.element("nodes", index)?;
+        let transform = parent * local_matrix(node);
+        globals.insert(index, transform);
+        nodes.push(index);
+
+        for child in node.get("children").and_then(Json::as_array).unwrap_or(&[]).iter().rev().filter_map(Json::as_usize) {
+            parents.insert(child, index);
+            stack.push((child, transform));
+        }
+    }
+
+    // primitives, materials and skins shared between nodes are only created once
+    // each primitive's mesh and how many joints its vertices use
+    let mut primitives: HashMap<(usize, usize), (MeshHandle, usize)> = HashMap::new();
+    let mut materials: HashMap<(Option<usize>, bool), (MaterialHandle, f32)> = HashMap::new();
+    let mut skeletons: HashMap<usize, Rc<Skeleton>> = HashMap::new();
+    let mut names = HashSet::new();
+    let mut parts = Vec::new();
+
+    let mut model_nodes = Vec::with_capacity(nodes.len());
+    let mut model_node_of = HashMap::new();
+    for &index in &nodes {
+        model_node_of.insert(index, model_nodes.len());
+        model_nodes.push(ModelNode {
+            name: gltf.node_name(index),
+            transform: local_matrix(gltf.element("nodes", index)?),
+            parent: parents.get(&index).map(|parent| model_node_of[parent]),
+        });
+    }
+
+    for index in nodes {
+        let node = gltf.element("nodes", index)?;
+        let Some(mesh_index) = node.get("mesh").and_then(Json::as_usize) else { continue };
+        let mesh = gltf.element("meshes", mesh_index)?;
+        let mesh_primitives = mesh.get("primitives").and_then(Json::as_array).unwrap_or(&[]);
+
+        let skeleton = match node.get("skin").and_then(Json::as_usize) {
+            Some(skin) => match skeletons.get(&skin) {
+                Some(skeleton) => Some(skeleton.clone()),
+                None => {
+                    let skeleton = Rc::new(gltf.create_skeleton(skin, &parents, &globals)?);
+                    skeletons.insert(skin, skeleton.clone());
+                    Some(skeleton)
+                },
+            },
+            None => None,
+        };
+
+        let node_name = gltf.node_name(index);
+        for (p, primitive) in mesh_primitives.iter().enumerate() {
+            let mut name = format!("{}.{}", node_name, p);
+            if !names.insert(name.clone()) {
+                name = format!("{}#{}", name, index);
+                names.insert(name.clone());
+            }
+
+            let (mesh, joints) = match primitives.get(&(mesh_index, p)) {
+                Some(existing) => existing.clone(),
+                None => {
+                    let data = gltf.read_primitive(primitive, &format!("mesh {} primitive {}", mesh_index, p))?;
+                    let joints = data.vertices.iter().flat_map(|vertex| vertex.joints).max().map_or(0, |joint| joint as usize + 1);
+                    let mesh = assets.add_mesh(&format!("{}:mesh{}.{}", file, mesh_index, p), move || data);
+                    primitives.insert((mesh_index, p), (mesh.clone(), joints));
+                    (mesh, joints)
+                },
+            };
+            // the same mesh can be skinned by different skins, so this is checked for every node using it
+            if let Some(skeleton) = &skeleton {
+                if joints > skeleton.joints().len() {
+                    return Err(gltf.error(format!("node {} uses joint {} of a skin with {} joints", index, joints - 1, skeleton.joints().len())));
+                }
+            }
+
+            let key = (primitive.get("material").and_then(Json::as_usize), skeleton.is_some());
+            let (material, alpha) = match materials.get(&key) {
+                Some(existing) => *existing,
+                // the default material doesn't need a new one unless it's skinned
+                None if key == (None, false) => (MaterialHandle::default(), 1.),
+                None => {
+                    let created = gltf.create_material(assets, key.0, key.1)?;
+                    materials.insert(key, created);
+                    created
+                },
+            };
+
+            parts.push(ModelPart {
+                name,
+                mesh,
+                material,
+                color: Vec4::new(1., 1., 1., alpha),
+                transform: Mat4::IDENTITY,
+                // skinned meshes are placed by their joints, not their node
+                node: if skeleton.is_some() { None } else { Some(model_node_of[&index]) },
+                skeleton: skeleton.clone(),
+            });
+        }
+    }
+
+    let animations = (0..gltf.array("animations").len())
+        .map(|index| gltf.read_animation(index).map(Rc::new))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Model { nodes: model_nodes, parts, animations })
+}
@@ -1,8 +1,10 @@
-use std::{ffi::{CStr, CString}, ptr::{null, null_mut}};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, ffi::{CStr, CString}, ptr::{null, null_mut}};
 
 use gl::types::{GLchar, GLenum, GLint, GLuint};
 
-use glam::Vec3;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::uniform_buffer::BLOCK_BINDINGS;
 
 // an opengl shader
 pub struct Shader {
@@ -49,13 +51,24 @@ impl Drop for Shader {
     }
 }
 
+// an active uniform or attribute found by reflecting a linked program
+#[derive(Clone, Copy, Debug)]
+pub struct VariableInfo {
+    pub location: GLint,
+    pub kind: GLenum, // e.g. gl::FLOAT_VEC3
+    pub size: GLint,  // number of array elements, 1 for non-arrays
+}
+
 // a sequence of shaders calls
 pub struct Program {
     id: GLuint,
+    uniforms: HashMap<String, VariableInfo>,
+    attributes: HashMap<String, VariableInfo>,
+    warned: RefCell<HashSet<String>>, // uniforms a warning was already printed for
 }
 
 impl Program {
-    fn from_shaders(shaders: &[Shader]) -> Result<Self, String> {
+    pub fn from_shaders(shaders: &[Shader]) -> Result<Self, String> {
         // create, attach, and link program
 
         let id = unsafe { gl::CreateProgram() };
@@ -88,16 +101,192 @@ impl Program {
             }
         }
 
-        Ok(Program { id })
+        // attach the engine's uniform blocks to their fixed binding points
+        for (block, binding) in BLOCK_BINDINGS {
+            let cname = CString::new(*block).expect("CString::new failed.");
+            let index = unsafe { gl::GetUniformBlockIndex(id, cname.as_ptr()) };
+            if index != gl::INVALID_INDEX {
+                unsafe { gl::UniformBlockBinding(id, index, *binding); }
+            }
+        }
+
+        Ok(Program {
+            id,
+            uniforms: reflect_uniforms(id),
+            attributes: reflect_attributes(id),
+            warned: RefCell::new(HashSet::new()),
+        })
+    }
+
+    pub fn from_sources(vert_source: &str, frag_source: &str) -> Result<Self, String> {
+        let vert_cstring = CString::new(vert_source).map_err(|e| e.to_string())?;
+        let frag_cstring = CString::new(frag_source).map_err(|e| e.to_string())?;
+
+        let vert_shader = Shader::from_source(&vert_cstring, gl::VERTEX_SHADER)?;
+        let frag_shader = Shader::from_source(&frag_cstring, gl::FRAGMENT_SHADER)?;
+
+        Program::from_shaders(&[vert_shader, frag_shader])
     }
 
     pub fn set(&self) {
         unsafe { gl::UseProgram(self.id); }
     }
 
+    #[allow(dead_code)]
     pub fn id(&self) -> GLuint {
         self.id
     }
+
+    // reflection for games and tools, the renderer only checks uniforms through has_uniform
+    #[allow(dead_code)]
+    pub fn uniforms(&self) -> &HashMap<String, VariableInfo> {
+        &self.uniforms
+    }
+
+    #[allow(dead_code)]
+    pub fn attributes(&self) -> &HashMap<String, VariableInfo> {
+        &self.attributes
+    }
+
+    // false if the uniform isn't declared or was optimized out, for setting uniforms a shader may not use
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.uniforms.contains_key(name)
+    }
+
+    #[allow(dead_code)]
+    pub fn attribute_location(&self, name: &str) -> Option<GLint> {
+        self.attributes.get(name).map(|attribute| attribute.location)
+    }
+
+    // the location of `name` if it's active and one of `kinds`, otherwise prints a warning the first time
+    fn location(&self, name: &str, kinds: &[GLenum]) -> Option<GLint> {
+        let warning = match self.uniforms.get(name) {
+            Some(uniform) if kinds.contains(&uniform.kind) => return Some(uniform.location),
+            Some(uniform) => format!("has uniform {} of type 0x{:x}, which can't be set to 0x{:x}", name, uniform.kind, kinds[0]),
+            None => format!("has no active uniform {}, it may have been optimized out", name),
+        };
+        if self.warned.borrow_mut().insert(name.to_string()) {
+            println!("Warning: program {} {}", self.id, warning);
+        }
+        None
+    }
+
+    // the following setters require the program to be in use
+
+    pub fn set_int(&self, name: &str, value: i32) {
+        if let Some(location) = self.location(name, &[gl::INT, gl::BOOL, gl::SAMPLER_2D]) {
+            unsafe { gl::Uniform1i(location, value); }
+        }
+    }
+
+    pub fn set_bool(&self, name: &str, value: bool) {
+        if let Some(location) = self.location(name, &[gl::BOOL, gl::INT]) {
+            unsafe { gl::Uniform1i(location, value as GLint); }
+        }
+    }
+
+    pub fn set_float(&self, name: &str, value: f32) {
+        if let Some(location) = self.location(name, &[gl::FLOAT]) {
+            unsafe { gl::Uniform1f(location, value); }
+        }
+    }
+
+    pub fn set_vec2(&self, name: &str, value: Vec2) {
+        if let Some(location) = self.location(name, &[gl::FLOAT_VEC2]) {
+            unsafe { gl::Uniform2fv(location, 1, value.to_array().as_ptr()); }
+        }
+    }
+
+    pub fn set_vec3(&self, name: &str, value: Vec3) {
+        if let Some(location) = self.location(name, &[gl::FLOAT_VEC3]) {
+            unsafe { gl::Uniform3fv(location, 1, value.to_array().as_ptr()); }
+        }
+    }
+
+    pub fn set_vec4(&self, name: &str, value: Vec4) {
+        if let Some(location) = self.location(name, &[gl::FLOAT_VEC4]) {
+            unsafe { gl::Uniform4fv(location, 1, value.to_array().as_ptr()); }
+        }
+    }
+
+    pub fn set_mat4(&self, name: &str, value: &Mat4) {
+        if let Some(location) = self.location(name, &[gl::FLOAT_MAT4]) {
+            unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, value.to_cols_array().as_ptr()); }
+        }
+    }
+
+    // sets the elements of the array `name` from its first, up to the array's declared size
+    pub fn set_mat4_array(&self, name: &str, values: &[Mat4]) {
+        if let Some(location) = self.location(name, &[gl::FLOAT_MAT4]) {
+            let count = values.len().min(self.uniforms[name].size as usize);
+            let columns: Vec<f32> = values[..count].iter().flat_map(Mat4::to_cols_array).collect();
+            unsafe { gl::UniformMatrix4fv(location, count as GLint, gl::FALSE, columns.as_ptr()); }
+        }
+    }
+
+    // binds `texture` to `unit` and points the sampler `name` at it
+    pub fn set_texture(&self, name: &str, unit: u32, texture: &Texture) {
+        if let Some(location) = self.location(name, &[gl::SAMPLER_2D]) {
+            texture.bind(unit);
+            unsafe { gl::Uniform1i(location, unit as GLint); }
+        }
+    }
+}
+
+fn reflect_uniforms(program: GLuint) -> HashMap<String, VariableInfo> {
+    let mut count: GLint = 0;
+    unsafe { gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count); }
+
+    let mut uniforms = HashMap::new();
+    for i in 0..count as GLuint {
+        let (name, kind, size) = active_variable(program, i, gl::ACTIVE_UNIFORM_MAX_LENGTH, gl::GetActiveUniform);
+        let location = unsafe { gl::GetUniformLocation(program, CString::new(name.as_str()).unwrap().as_ptr()) };
+        if location == -1 {
+            continue; // members of uniform blocks don't have locations
+        }
+
+        // arrays are reported as "name[0]", make them reachable as "name" and by each element
+        if let Some(base) = name.strip_suffix("[0]") {
+            for element in 1..size {
+                let element_name = format!("{}[{}]", base, element);
+                let element_location = unsafe { gl::GetUniformLocation(program, CString::new(element_name.as_str()).unwrap().as_ptr()) };
+                uniforms.insert(element_name, VariableInfo { location: element_location, kind, size: 1 });
+            }
+            uniforms.insert(base.to_string(), VariableInfo { location, kind, size });
+        }
+        uniforms.insert(name, VariableInfo { location, kind, size });
+    }
+    uniforms
+}
+
+fn reflect_attributes(program: GLuint) -> HashMap<String, VariableInfo> {
+    let mut count: GLint = 0;
+    unsafe { gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTES, &mut count); }
+
+    let mut attributes = HashMap::new();
+    for i in 0..count as GLuint {
+        let (name, kind, size) = active_variable(program, i, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, gl::GetActiveAttrib);
+        let location = unsafe { gl::GetAttribLocation(program, CString::new(name.as_str()).unwrap().as_ptr()) };
+        attributes.insert(name, VariableInfo { location, kind, size });
+    }
+    attributes
+}
+
+type GetActiveFn = unsafe fn(GLuint, GLuint, GLint, *mut GLint, *mut GLint, *mut GLenum, *mut GLchar);
+
+// name, type, and array size of the i-th active uniform or attribute
+fn active_variable(program: GLuint, index: GLuint, max_length: GLenum, get_active: GetActiveFn) -> (String, GLenum, GLint) {
+    let mut buffer_len: GLint = 0;
+    unsafe { gl::GetProgramiv(program, max_length, &mut buffer_len); }
+
+    let mut name = vec![0u8; buffer_len.max(1) as usize];
+    let mut len: GLint = 0;
+    let mut size: GLint = 0;
+    let mut kind: GLenum = 0;
+    unsafe { get_active(program, index, buffer_len, &mut len, &mut size, &mut kind, name.as_mut_ptr() as *mut GLchar); }
+
+    name.truncate(len as usize);
+    (String::from_utf8_lossy(&name).into_owned(), kind, size)
 }
 
 impl Drop for Program {
@@ -112,16 +301,6 @@ fn create_whitespace_cstring_with_len(len: usize) -> CString {
     unsafe { CString::from_vec_unchecked(buffer) }
 }
 
-pub fn create_program() -> Result<Program, &'static str> {
-    let vert_shader = Shader::from_source(&CString::new(include_str!(".vert")).unwrap(), gl::VERTEX_SHADER).unwrap();
-    let frag_shader = Shader::from_source(&CString::new(include_str!(".frag")).unwrap(), gl::FRAGMENT_SHADER).unwrap();
-
-    let shader_program = Program::from_shaders(&[vert_shader, frag_shader]).unwrap();
-
-    Ok(shader_program)
-}
-
-#[derive(Clone)]
 // vertex buffer object
 pub struct Vbo { 
     pub id: GLuint,
@@ -134,16 +313,16 @@ impl Vbo {
         Vbo { id }
     }
 
-    pub fn set(&self, data: &Vec<Vec3>) {
+    pub fn set<T: Copy>(&self, data: &[T]) {
         self.bind();
         self.data(data);
     }
 
-    fn data(&self, vertices: &Vec<Vec3>) {
+    fn data<T: Copy>(&self, vertices: &[T]) {
         unsafe {
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (vertices.len() * std::mem::size_of::<Vec3>()) as gl::types::GLsizeiptr,
+                std::mem::size_of_val(vertices) as gl::types::GLsizeiptr,
                 vertices.as_ptr() as *const gl::types::GLvoid,
                 gl::DYNAMIC_DRAW
             );
@@ -170,7 +349,6 @@ impl Drop for Vbo {
     }
 }
 
-#[derive(Clone)]
 // index buffer object
 pub struct Ibo { 
     pub id: GLuint,
@@ -183,16 +361,16 @@ impl Ibo {
         Ibo { id }
     }
 
-    pub fn set(&self, data: &Vec<u32>) {
+    pub fn set(&self, data: &[u32]) {
         self.bind();
         self.data(data);
     }
 
-    fn data(&self, indices: &Vec<u32>) {
+    fn data(&self, indices: &[u32]) {
         unsafe {
             gl::BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
-                (indices.len() * std::mem::size_of::<u32>()) as gl::types::GLsizeiptr,
+                std::mem::size_of_val(indices) as gl::types::GLsizeiptr,
                 indices.as_ptr() as *const gl::types::GLvoid,
                 gl::STATIC_DRAW
             );
@@ -219,7 +397,36 @@ impl Drop for Ibo {
     }
 }
 
-#[derive(Clone)]
+// attribute locations of the per-vertex data, must match the vertex shaders
+pub const POSITION_LOCATION: GLuint = 0;
+pub const NORMAL_LOCATION: GLuint = 6; // after the per-instance locations
+pub const UV_LOCATION: GLuint = 7;
+pub const JOINTS_LOCATION: GLuint = 8;
+pub const WEIGHTS_LOCATION: GLuint = 9;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+// interleaved per-vertex data of every mesh
+pub struct Vertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+    pub joints: [u16; 4], // skeleton joints moving the vertex, only read by skinned programs
+    pub weights: Vec4,    // how much each of `joints` moves the vertex, summing to 1
+}
+
+// attribute locations of the per-instance data, must match shaders/instancing.glsl
+pub const INSTANCE_MODEL_LOCATION: GLuint = 1; // occupies 1 through 4
+pub const INSTANCE_COLOR_LOCATION: GLuint = 5;
+
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+// per-instance vertex data for instanced draws
+pub struct InstanceData {
+    pub model_matrix: Mat4,
+    pub color: Vec4,
+}
+
 // vertex array object
 pub struct Vao { 
     pub id: GLuint,
@@ -237,21 +444,72 @@ impl Vao {
         self.setup();
     }
 
-    /// this function should be manually modified whenever the layout of the vertex buffer changes
+    /// this function should be manually modified whenever the layout of Vertex changes
     fn setup(&self) {
-        let stride = std::mem::size_of::<Vec3>() as GLint;
+        let stride = std::mem::size_of::<Vertex>() as GLint;
+        let attributes = [
+            (POSITION_LOCATION, 3, std::mem::offset_of!(Vertex, position)),
+            (NORMAL_LOCATION, 3, std::mem::offset_of!(Vertex, normal)),
+            (UV_LOCATION, 2, std::mem::offset_of!(Vertex, uv)),
+            (WEIGHTS_LOCATION, 4, std::mem::offset_of!(Vertex, weights)),
+        ];
         unsafe {
+            for (location, size, offset) in attributes {
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribPointer(
+                    location,
+                    size,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    offset as *const gl::types::GLvoid,
+                );
+            }
 
-            gl::EnableVertexAttribArray(0);
-            // Position
+            // Joints, read as integers
+            gl::EnableVertexAttribArray(JOINTS_LOCATION);
+            gl::VertexAttribIPointer(
+                JOINTS_LOCATION,
+                4,
+                gl::UNSIGNED_SHORT,
+                stride,
+                std::mem::offset_of!(Vertex, joints) as *const gl::types::GLvoid,
+            );
+        }
+    }
+
+    /// this function should be manually modified whenever the layout of InstanceData changes
+    pub fn setup_instances(&self, instances: &Vbo) {
+        self.bind();
+        instances.bind();
+        let stride = std::mem::size_of::<InstanceData>() as GLint;
+        unsafe {
+            // Model matrix, one vec4 column per location
+            for column in 0..4 {
+                let location = INSTANCE_MODEL_LOCATION + column;
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribPointer(
+                    location,
+                    4,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    (column as usize * std::mem::size_of::<Vec4>()) as *const gl::types::GLvoid,
+                );
+                gl::VertexAttribDivisor(location, 1);
+            }
+
+            // Color
+            gl::EnableVertexAttribArray(INSTANCE_COLOR_LOCATION);
             gl::VertexAttribPointer(
-                0, 
-                3, 
-                gl::FLOAT, 
+                INSTANCE_COLOR_LOCATION,
+                4,
+                gl::FLOAT,
                 gl::FALSE,
                 stride,
-                null(),
+                std::mem::offset_of!(InstanceData, color) as *const gl::types::GLvoid,
             );
+            gl::VertexAttribDivisor(INSTANCE_COLOR_LOCATION, 1);
         }
     }
 
@@ -275,17 +533,388 @@ impl Drop for Vao {
     }
 }
 
-pub struct Uniform {
-    pub id: GLint,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+// storage format of a texture or renderbuffer
+pub enum TextureFormat {
+    Rgba8,
+    Rgba16F,
+    Depth24,
+    Depth24Stencil8,
 }
 
-impl Uniform {
-    pub fn new(program: u32, name: &str) -> Result<Self, String> {
-        let cname = CString::new(name).expect("CString::new failed.");
-        let location: GLint = unsafe { gl::GetUniformLocation(program, cname.as_ptr()) };
-        if location == -1 {
-            return Err(format!("Couldn't get uniform location for {}", name));
+impl TextureFormat {
+    fn internal_format(&self) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 => gl::RGBA8,
+            TextureFormat::Rgba16F => gl::RGBA16F,
+            TextureFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            TextureFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+        }
+    }
+
+    fn format(&self) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Rgba16F => gl::RGBA,
+            TextureFormat::Depth24 => gl::DEPTH_COMPONENT,
+            TextureFormat::Depth24Stencil8 => gl::DEPTH_STENCIL,
+        }
+    }
+
+    fn data_type(&self) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 => gl::UNSIGNED_BYTE,
+            TextureFormat::Rgba16F => gl::FLOAT,
+            TextureFormat::Depth24 => gl::UNSIGNED_INT,
+            TextureFormat::Depth24Stencil8 => gl::UNSIGNED_INT_24_8,
+        }
+    }
+
+    fn attachment_point(&self, index: usize) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Rgba16F => gl::COLOR_ATTACHMENT0 + index as GLenum,
+            TextureFormat::Depth24 => gl::DEPTH_ATTACHMENT,
+            TextureFormat::Depth24Stencil8 => gl::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+}
+
+// a 2d opengl texture
+pub struct Texture {
+    pub id: GLuint,
+    width: i32,
+    height: i32,
+    format: TextureFormat,
+}
+
+impl Texture {
+    pub fn new(width: i32, height: i32, format: TextureFormat) -> Self {
+        Texture::with_data(width, height, format, null())
+    }
+
+    // allocates the texture and fills it from `pixels` in one upload, or leaves it undefined if it's null
+    fn with_data(width: i32, height: i32, format: TextureFormat, pixels: *const gl::types::GLvoid) -> Self {
+        let mut id: GLuint = 0;
+        unsafe { gl::GenTextures(1, &mut id); }
+        let texture = Texture { id, width, height, format };
+        texture.bind(0);
+        texture.data(pixels);
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+        }
+        texture
+    }
+
+    // `pixels` holds 4 bytes per texel, bottom row first
+    pub fn from_rgba(width: i32, height: i32, pixels: &[u8]) -> Result<Self, String> {
+        let expected = width.max(0) as usize * height.max(0) as usize * 4;
+        if pixels.len() != expected {
+            return Err(format!("Couldn't create a {}x{} texture from {} bytes, expected {}", width, height, pixels.len(), expected));
+        }
+        Ok(Texture::with_data(width, height, TextureFormat::Rgba8, pixels.as_ptr() as *const gl::types::GLvoid))
+    }
+
+    fn data(&self, pixels: *const gl::types::GLvoid) {
+        unsafe {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                self.format.internal_format() as GLint,
+                self.width,
+                self.height,
+                0,
+                self.format.format(),
+                self.format.data_type(),
+                pixels,
+            );
+        }
+    }
+
+    // reallocates storage, discarding the previous contents
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+        self.bind(0);
+        self.data(null());
+    }
+
+    // samples outside the texture return `color` instead of the nearest edge texel
+    pub fn clamp_to_border(&self, color: [f32; 4]) {
+        self.bind(0);
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as GLint);
+            gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, color.as_ptr());
+        }
+    }
+
+    // samples outside the texture wrap around, for tiling texture coordinates
+    pub fn repeat(&self) {
+        self.bind(0);
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
+        }
+    }
+
+    // builds mipmaps from the current contents and samples them when minifying
+    pub fn generate_mipmaps(&self) {
+        self.bind(0);
+        unsafe {
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint);
+        }
+    }
+
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn delete(&self) {
+        unsafe { gl::DeleteTextures(1, &self.id); }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.delete();
+    }
+}
+
+// a render target that can't be sampled, cheaper than a texture when only used for depth testing
+pub struct Renderbuffer {
+    pub id: GLuint,
+    format: TextureFormat,
+}
+
+impl Renderbuffer {
+    pub fn new(width: i32, height: i32, format: TextureFormat) -> Self {
+        let mut id: GLuint = 0;
+        unsafe { gl::GenRenderbuffers(1, &mut id); }
+        let renderbuffer = Renderbuffer { id, format };
+        renderbuffer.storage(width, height);
+        renderbuffer
+    }
+
+    fn storage(&self, width: i32, height: i32) {
+        self.bind();
+        unsafe { gl::RenderbufferStorage(gl::RENDERBUFFER, self.format.internal_format(), width, height); }
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.storage(width, height);
+    }
+
+    pub fn bind(&self) {
+        unsafe { gl::BindRenderbuffer(gl::RENDERBUFFER, self.id); }
+    }
+
+    pub fn unbind(&self) {
+        unsafe { gl::BindRenderbuffer(gl::RENDERBUFFER, 0); }
+    }
+
+    pub fn delete(&self) {
+        unsafe { gl::DeleteRenderbuffers(1, &self.id); }
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        self.unbind();
+        self.delete();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+// describes how a framebuffer attachment should be created
+pub enum AttachmentKind {
+    Texture(TextureFormat),
+    Renderbuffer(TextureFormat),
+}
+
+pub enum Attachment {
+    Texture(Texture),
+    Renderbuffer(Renderbuffer),
+}
+
+impl Attachment {
+    fn new(kind: AttachmentKind, width: i32, height: i32) -> Self {
+        match kind {
+            AttachmentKind::Texture(format) => Attachment::Texture(Texture::new(width, height, format)),
+            AttachmentKind::Renderbuffer(format) => Attachment::Renderbuffer(Renderbuffer::new(width, height, format)),
+        }
+    }
+
+    fn attach(&self, index: usize) {
+        unsafe {
+            match self {
+                Attachment::Texture(texture) => {
+                    gl::FramebufferTexture2D(gl::FRAMEBUFFER, texture.format.attachment_point(index), gl::TEXTURE_2D, texture.id, 0);
+                },
+                Attachment::Renderbuffer(renderbuffer) => {
+                    gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, renderbuffer.format.attachment_point(index), gl::RENDERBUFFER, renderbuffer.id);
+                },
+            }
+        }
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        match self {
+            Attachment::Texture(texture) => texture.resize(width, height),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.resize(width, height),
+        }
+    }
+
+    pub fn texture(&self) -> Option<&Texture> {
+        match self {
+            Attachment::Texture(texture) => Some(texture),
+            Attachment::Renderbuffer(_) => None,
+        }
+    }
+}
+
+// an offscreen render target, e.g. for mirrors, minimaps, post-processing or readback
+pub struct Framebuffer {
+    pub id: GLuint,
+    width: i32,
+    height: i32,
+    color: Vec<Attachment>,
+    depth: Option<Attachment>,
+}
+
+impl Framebuffer {
+    pub fn new(width: i32, height: i32, color: &[AttachmentKind], depth: Option<AttachmentKind>) -> Result<Self, String> {
+        let mut id: GLuint = 0;
+        unsafe { gl::GenFramebuffers(1, &mut id); }
+
+        let framebuffer = Framebuffer {
+            id,
+            width,
+            height,
+            color: color.iter().map(|kind| Attachment::new(*kind, width, height)).collect(),
+            depth: depth.map(|kind| Attachment::new(kind, width, height)),
+        };
+
+        framebuffer.attach()?;
+        Ok(framebuffer)
+    }
+
+    fn attach(&self) -> Result<(), String> {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.id); }
+
+        for (i, attachment) in self.color.iter().enumerate() {
+            attachment.attach(i);
+        }
+        if let Some(depth) = &self.depth {
+            depth.attach(0);
+        }
+
+        // tell opengl which color attachments fragment outputs are written to
+        let draw_buffers: Vec<GLenum> = (0..self.color.len()).map(|i| gl::COLOR_ATTACHMENT0 + i as GLenum).collect();
+        unsafe {
+            if draw_buffers.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(draw_buffers.len() as GLint, draw_buffers.as_ptr());
+            }
+        }
+
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0); }
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(format!("Framebuffer {} is incomplete (status 0x{:x})", self.id, status));
+        }
+        Ok(())
+    }
+
+    // resizes every attachment, discarding their contents
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), String> {
+        if width == self.width && height == self.height {
+            return Ok(());
+        }
+        self.width = width;
+        self.height = height;
+        for attachment in self.color.iter_mut().chain(self.depth.iter_mut()) {
+            attachment.resize(width, height);
+        }
+        self.attach()
+    }
+
+    // binds the framebuffer as the render target and matches the viewport to its size
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    pub fn color_texture(&self, index: usize) -> Option<&Texture> {
+        self.color.get(index).and_then(|attachment| attachment.texture())
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth.as_ref().and_then(|attachment| attachment.texture())
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    // reads back a color attachment as tightly packed rgba8 rows, bottom row first
+    pub fn read_pixels(&self, index: usize) -> Vec<u8> {
+        let mut pixels = vec![0u8; (self.width * self.height * 4) as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + index as GLenum);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(0, 0, self.width, self.height, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut gl::types::GLvoid);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
         }
-        Ok(Uniform { id: location })
+        pixels
     }
-}
\ No newline at end of file
+
+    // copies a color attachment to the window's framebuffer, scaling to fit
+    pub fn blit_to_default(&self, index: usize, width: i32, height: i32) {
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + index as GLenum);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl::BlitFramebuffer(0, 0, self.width, self.height, 0, 0, width, height, gl::COLOR_BUFFER_BIT, gl::LINEAR);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0); }
+    }
+
+    pub fn delete(&self) {
+        unsafe { gl::DeleteFramebuffers(1, &self.id); }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.unbind();
+        self.delete();
+    }
+}
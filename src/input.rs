@@ -0,0 +1,521 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use glam::Vec2;
+use sdl2::controller::{Axis, Button as PadButton};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
+
+use crate::gamepad::{self, DeadZones, Stick, Trigger};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+// an input that's either held down or not
+pub enum Button {
+    Key(Keycode),
+    Mouse(MouseButton),
+    Pad(PadButton),
+}
+
+impl From<Keycode> for Button {
+    fn from(key: Keycode) -> Self {
+        Button::Key(key)
+    }
+}
+
+impl From<MouseButton> for Button {
+    fn from(button: MouseButton) -> Self {
+        Button::Mouse(button)
+    }
+}
+
+impl From<PadButton> for Button {
+    fn from(button: PadButton) -> Self {
+        Button::Pad(button)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+// an input with a value from -1 to 1
+pub enum AxisInput {
+    Buttons { negative: Button, positive: Button },
+    Pad(Axis),
+}
+
+// the resource games poll for input, updated by the engine from every event before the schedule runs
+// raw inputs are queried directly, and actions and axes through the input map
+#[derive(Default)]
+pub struct InputState {
+    held: HashSet<Button>,     // pad buttons are held while any pad holds them
+    pressed: HashSet<Button>,  // since the last frame
+    released: HashSet<Button>, // since the last frame
+    pad_buttons: HashSet<(u32, PadButton)>, // by pad id
+    pad_axes: HashMap<(u32, Axis), f32>,    // by pad id, from -1 to 1 before dead zones
+    pub dead_zones: DeadZones,
+    mouse_position: Vec2,
+    mouse_delta: Vec2,
+    wheel: Vec2,
+    map: InputMap,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        InputState::default()
+    }
+
+    #[allow(dead_code)]
+    pub fn map(&self) -> &InputMap {
+        &self.map
+    }
+
+    // for loading and rebinding
+    #[allow(dead_code)]
+    pub fn map_mut(&mut self) -> &mut InputMap {
+        &mut self.map
+    }
+
+    pub fn set_map(&mut self, map: InputMap) {
+        self.map = map;
+    }
+
+    // forgets the last frame's presses, releases and mouse motion, the engine calls this before polling events
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.wheel = Vec2::ZERO;
+    }
+
+    fn press(&mut self, button: Button) {
+        // key repeats aren't new presses
+        if self.held.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    fn release(&mut self, button: Button) {
+        if self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::KeyDown { keycode: Some(key), .. } => self.press(Button::Key(key)),
+            Event::KeyUp { keycode: Some(key), .. } => self.release(Button::Key(key)),
+            Event::MouseButtonDown { mouse_btn, .. } => self.press(Button::Mouse(mouse_btn)),
+            Event::MouseButtonUp { mouse_btn, .. } => self.release(Button::Mouse(mouse_btn)),
+            Event::ControllerButtonDown { which, button, .. } => {
+                self.pad_buttons.insert((which, button));
+                self.press(Button::Pad(button));
+            },
+            Event::ControllerButtonUp { which, button, .. } => {
+                self.pad_buttons.remove(&(which, button));
+                self.release_pad_button(button);
+            },
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                self.pad_axes.insert((which, axis), (value as f32 / i16::MAX as f32).max(-1.));
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                let buttons: Vec<PadButton> = self.pad_buttons.iter().filter(|(pad, _)| *pad == which).map(|(_, button)| *button).collect();
+                self.pad_buttons.retain(|(pad, _)| *pad != which);
+                self.pad_axes.retain(|(pad, _), _| *pad != which);
+                for button in buttons {
+                    self.release_pad_button(button);
+                }
+            },
+            Event::MouseMotion { x, y, xrel, yrel, .. } => {
+                self.mouse_position = Vec2::new(x as f32, y as f32);
+                self.mouse_delta += Vec2::new(xrel as f32, yrel as f32);
+            },
+            Event::MouseWheel { x, y, direction, .. } => {
+                let sign = if direction == MouseWheelDirection::Flipped { -1. } else { 1. };
+                self.wheel += Vec2::new(x as f32, y as f32) * sign;
+            },
+            // releases go to the focused window, so nothing stays held after switching away
+            Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+                let held: Vec<Button> = self.held.iter().copied().collect();
+                for button in held {
+                    self.release(button);
+                }
+                self.pad_buttons.clear();
+            },
+            _ => {},
+        }
+    }
+
+    // released once no pad holds it
+    fn release_pad_button(&mut self, button: PadButton) {
+        if !self.pad_buttons.iter().any(|(_, other)| *other == button) {
+            self.release(Button::Pad(button));
+        }
+    }
+
+    pub fn is_held(&self, button: impl Into<Button>) -> bool {
+        self.held.contains(&button.into())
+    }
+
+    #[allow(dead_code)]
+    pub fn just_pressed(&self, button: impl Into<Button>) -> bool {
+        self.pressed.contains(&button.into())
+    }
+
+    #[allow(dead_code)]
+    pub fn just_released(&self, button: impl Into<Button>) -> bool {
+        self.released.contains(&button.into())
+    }
+
+    fn raw_pad_axis(&self, pad: u32, axis: Axis) -> f32 {
+        self.pad_axes.get(&(pad, axis)).copied().unwrap_or(0.)
+    }
+
+    // the pad's stick position past the dead zone, each axis from -1 to 1 with y down
+    pub fn pad_stick(&self, pad: u32, stick: Stick) -> Vec2 {
+        let (x, y) = stick.axes();
+        gamepad::stick_dead_zone(Vec2::new(self.raw_pad_axis(pad, x), self.raw_pad_axis(pad, y)), self.dead_zones.stick)
+    }
+
+    // how far the pad's trigger is pulled past the dead zone, from 0 to 1
+    pub fn pad_trigger(&self, pad: u32, trigger: Trigger) -> f32 {
+        gamepad::trigger_dead_zone(self.raw_pad_axis(pad, trigger.axis()), self.dead_zones.trigger)
+    }
+
+    #[allow(dead_code)]
+    pub fn pad_button(&self, pad: u32, button: PadButton) -> bool {
+        self.pad_buttons.contains(&(pad, button))
+    }
+
+    // one of the pad's axes past its dead zone
+    pub fn pad_axis_of(&self, pad: u32, axis: Axis) -> f32 {
+        match axis {
+            Axis::LeftX => self.pad_stick(pad, Stick::Left).x,
+            Axis::LeftY => self.pad_stick(pad, Stick::Left).y,
+            Axis::RightX => self.pad_stick(pad, Stick::Right).x,
+            Axis::RightY => self.pad_stick(pad, Stick::Right).y,
+            Axis::TriggerLeft => self.pad_trigger(pad, Trigger::Left),
+            Axis::TriggerRight => self.pad_trigger(pad, Trigger::Right),
+        }
+    }
+
+    // the axis of whichever pad pushes it furthest, for games that don't tell pads apart, ties go to the lowest pad id
+    pub fn pad_axis(&self, axis: Axis) -> f32 {
+        let pads: BTreeSet<u32> = self.pad_axes.keys().map(|(pad, _)| *pad).collect();
+        pads.into_iter().map(|pad| self.pad_axis_of(pad, axis)).fold(0., |a, b| if b.abs() > a.abs() { b } else { a })
+    }
+
+    // in pixels from the window's top left corner
+    #[allow(dead_code)]
+    pub fn mouse_position(&self) -> Vec2 {
+        self.mouse_position
+    }
+
+    // pixels moved since the last frame
+    #[allow(dead_code)]
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    // scrolled since the last frame, positive y is away from the user
+    #[allow(dead_code)]
+    pub fn wheel(&self) -> Vec2 {
+        self.wheel
+    }
+
+    // whether any of the action's buttons is held
+    #[allow(dead_code)]
+    pub fn is_action_held(&self, name: &str) -> bool {
+        self.map.action_bindings(name).iter().any(|button| self.is_held(*button))
+    }
+
+    // whether any of the action's buttons was pressed since the last frame
+    #[allow(dead_code)]
+    pub fn action_just_pressed(&self, name: &str) -> bool {
+        self.map.action_bindings(name).iter().any(|button| self.just_pressed(*button))
+    }
+
+    #[allow(dead_code)]
+    pub fn action_just_released(&self, name: &str) -> bool {
+        self.map.action_bindings(name).iter().any(|button| self.just_released(*button))
+    }
+
+    // the sum of the axis's inputs, from -1 to 1
+    pub fn axis(&self, name: &str) -> f32 {
+        let value: f32 = self.map.axis_bindings(name).iter().map(|input| match *input {
+            AxisInput::Buttons { negative, positive } => self.is_held(positive) as i32 as f32 - self.is_held(negative) as i32 as f32,
+            AxisInput::Pad(axis) => self.pad_axis(axis),
+        }).sum();
+        value.clamp(-1., 1.)
+    }
+}
+
+// named actions and axes bound to inputs, so games ask for "jump" or "move_x" rather than matching events
+// bindings can be loaded from a config file and changed while the game runs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputMap {
+    actions: HashMap<String, Vec<Button>>,
+    axes: HashMap<String, Vec<AxisInput>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        InputMap::default()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        InputMap::parse(&source, &path.display().to_string())
+    }
+
+    // bindings, one action or axis per line:
+    //   action <name> = <button>, ...
+    //   axis <name> = <axis input>, ...
+    // buttons are key:<SDL key name>, mouse:<left|middle|right|x1|x2|unknown> or pad:<SDL controller button name>
+    // axis inputs are <negative button>/<positive button> or padaxis:<SDL controller axis name>
+    // key names with '#', ',', '/' or '"' in them are written in double quotes, with '"' and '\' escaped by '\'
+    // `file` names the source in errors
+    pub fn parse(source: &str, file: &str) -> Result<Self, String> {
+        let mut map = InputMap::new();
+        for (number, line) in source.lines().enumerate() {
+            let error = |message: String| format!("{}:{}: {}", file, number + 1, message);
+            let line = split_unquoted(line, '#')[0].trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (declaration, bindings) = line.split_once('=').ok_or_else(|| error("expected '='".to_string()))?;
+            let mut words = declaration.split_whitespace();
+            let (Some(kind), Some(name), None) = (words.next(), words.next(), words.next()) else {
+                return Err(error("expected \"action <name>\" or \"axis <name>\" before '='".to_string()));
+            };
+            let bindings = split_unquoted(bindings, ',').into_iter().map(str::trim).filter(|binding| !binding.is_empty());
+
+            match kind {
+                "action" => {
+                    map.actions.entry(name.to_string()).or_default();
+                    for binding in bindings {
+                        map.bind_action(name, parse_button(binding).map_err(error)?);
+                    }
+                },
+                "axis" => {
+                    map.axes.entry(name.to_string()).or_default();
+                    for binding in bindings {
+                        map.bind_axis(name, parse_axis_input(binding).map_err(error)?);
+                    }
+                },
+                _ => return Err(error(format!("unknown binding kind \"{}\"", kind))),
+            }
+        }
+        Ok(map)
+    }
+
+    // the bindings in the format parse reads
+    #[allow(dead_code)]
+    pub fn to_config(&self) -> String {
+        let join = |bindings: Vec<String>| bindings.join(", ");
+        let mut actions: Vec<String> = self.actions.iter()
+            .map(|(name, buttons)| format!("action {} = {}", name, join(buttons.iter().map(Button::to_string).collect())))
+            .collect();
+        let mut axes: Vec<String> = self.axes.iter()
+            .map(|(name, inputs)| format!("axis {} = {}", name, join(inputs.iter().map(AxisInput::to_string).collect())))
+            .collect();
+        actions.sort();
+        axes.sort();
+        actions.into_iter().chain(axes).map(|line| line + "\n").collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_config()).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+    }
+
+    pub fn bind_action(&mut self, name: &str, button: Button) {
+        let buttons = self.actions.entry(name.to_string()).or_default();
+        if !buttons.contains(&button) {
+            buttons.push(button);
+        }
+    }
+
+    pub fn bind_axis(&mut self, name: &str, input: AxisInput) {
+        let inputs = self.axes.entry(name.to_string()).or_default();
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+
+    // removes every binding of the action or axis, for rebinding it
+    #[allow(dead_code)]
+    pub fn clear_bindings(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+
+    #[allow(dead_code)]
+    pub fn action_bindings(&self, name: &str) -> &[Button] {
+        self.actions.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis_bindings(&self, name: &str) -> &[AxisInput] {
+        self.axes.get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+const MOUSE_BUTTONS: [(&str, MouseButton); 6] = [
+    ("left", MouseButton::Left),
+    ("middle", MouseButton::Middle),
+    ("right", MouseButton::Right),
+    ("x1", MouseButton::X1),
+    ("x2", MouseButton::X2),
+    ("unknown", MouseButton::Unknown), // any button past x2
+];
+
+// splits at every `separator` outside double quotes
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+// quotes names the config format would otherwise split or trim
+fn quote(name: &str) -> String {
+    if name.is_empty() || name.trim() != name || name.contains(['#', ',', '/', '"', '\\']) {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        name.to_string()
+    }
+}
+
+fn unquote(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    let Some(quoted) = name.strip_prefix('"') else { return Ok(name.to_string()) };
+    let mut unquoted = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            '"' if chars.as_str().is_empty() => return Ok(unquoted),
+            '"' => break,
+            c => unquoted.push(c),
+        }
+    }
+    Err(format!("expected a closing '\"' at the end of {}", name))
+}
+
+fn parse_button(text: &str) -> Result<Button, String> {
+    let (kind, name) = text.split_once(':').ok_or_else(|| format!("expected \"<kind>:<name>\" in \"{}\"", text))?;
+    let button = match kind.trim() {
+        "key" => Keycode::from_name(&unquote(name)?).map(Button::Key),
+        "mouse" => MOUSE_BUTTONS.iter().find(|(other, _)| other.eq_ignore_ascii_case(name.trim())).map(|(_, button)| Button::Mouse(*button)),
+        "pad" => PadButton::from_string(name.trim()).map(Button::Pad),
+        _ => return Err(format!("unknown button kind \"{}\"", kind)),
+    };
+    button.ok_or_else(|| format!("unknown {} \"{}\"", kind, name))
+}
+
+fn parse_axis_input(text: &str) -> Result<AxisInput, String> {
+    if let [negative, positive] = split_unquoted(text, '/')[..] {
+        return Ok(AxisInput::Buttons { negative: parse_button(negative.trim())?, positive: parse_button(positive.trim())? });
+    }
+    match text.split_once(':') {
+        Some(("padaxis", name)) => Axis::from_string(name.trim()).map(AxisInput::Pad).ok_or_else(|| format!("unknown padaxis \"{}\"", name)),
+        _ => Err(format!("expected \"<button>/<button>\" or \"padaxis:<name>\" in \"{}\"", text)),
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Button::Key(key) => write!(f, "key:{}", quote(&key.name())),
+            Button::Mouse(button) => {
+                let name = MOUSE_BUTTONS.iter().find(|(_, other)| other == button).map_or("unknown", |(name, _)| name);
+                write!(f, "mouse:{}", name)
+            },
+            Button::Pad(button) => write!(f, "pad:{}", button.string()),
+        }
+    }
+}
+
+impl fmt::Display for AxisInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AxisInput::Buttons { negative, positive } => write!(f, "{}/{}", negative, positive),
+            AxisInput::Pad(axis) => write!(f, "padaxis:{}", axis.string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(map: &InputMap) -> InputMap {
+        InputMap::parse(&map.to_config(), "test").unwrap_or_else(|e| panic!("{}\n{}", e, map.to_config()))
+    }
+
+    #[test]
+    fn every_kind_of_binding_round_trips() {
+        let mut map = InputMap::new();
+        map.bind_action("jump", Button::Key(Keycode::SPACE));
+        map.bind_action("jump", Button::Pad(PadButton::A));
+        map.bind_action("fire", Button::Mouse(MouseButton::Left));
+        map.bind_action("fire", Button::Mouse(MouseButton::Unknown));
+        map.bind_action("run", Button::Key(Keycode::LSHIFT));
+        map.bind_action("unbound", Button::Key(Keycode::A));
+        map.clear_bindings("unbound");
+        map.bind_axis("move_x", AxisInput::Buttons { negative: Button::Key(Keycode::A), positive: Button::Key(Keycode::D) });
+        map.bind_axis("move_x", AxisInput::Buttons { negative: Button::Pad(PadButton::DPadLeft), positive: Button::Pad(PadButton::DPadRight) });
+        map.bind_axis("move_x", AxisInput::Pad(Axis::LeftX));
+        assert_eq!(round_trip(&map), map);
+    }
+
+    #[test]
+    fn keys_named_like_separators_round_trip() {
+        let mut map = InputMap::new();
+        for key in [Keycode::HASH, Keycode::COMMA, Keycode::SLASH, Keycode::QUOTEDBL, Keycode::BACKSLASH] {
+            map.bind_action("symbol", Button::Key(key));
+        }
+        map.bind_axis("symbols", AxisInput::Buttons { negative: Button::Key(Keycode::SLASH), positive: Button::Key(Keycode::COMMA) });
+        assert_eq!(round_trip(&map), map);
+    }
+
+    #[test]
+    fn parse_reads_comments_quotes_and_empty_bindings() {
+        let map = InputMap::parse("# bindings\naction jump = key:Space, pad:a # either\naction menu =\naxis turn = key:\"/\"/key:\"#\"\n", "test").unwrap();
+        assert_eq!(map.action_bindings("jump"), &[Button::Key(Keycode::SPACE), Button::Pad(PadButton::A)]);
+        assert_eq!(map.action_bindings("menu"), &[]);
+        assert_eq!(map.axis_bindings("turn"), &[AxisInput::Buttons { negative: Button::Key(Keycode::SLASH), positive: Button::Key(Keycode::HASH) }]);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_lines() {
+        for line in [
+            "action jump",
+            "jump = key:Space",
+            "button jump = key:Space",
+            "action jump = space",
+            "action jump = key:NotAKey",
+            "action jump = mouse:fourth",
+            "action jump = key:\"#",
+            "axis turn = key:A/key:D/key:W",
+            "axis turn = padaxis:sideways",
+        ] {
+            assert!(InputMap::parse(line, "test").is_err(), "{} was accepted", line);
+        }
+    }
+}
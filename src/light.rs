@@ -0,0 +1,94 @@
+use glam::{Mat4, Vec3};
+
+use crate::uniform_buffer::LightData;
+
+#[derive(Clone, Copy, Debug)]
+pub enum LightKind {
+    // parallel rays, shadows are rendered with an orthographic camera covering `extent` around `center`
+    Directional { direction: Vec3, center: Vec3, extent: f32 },
+    // a cone of light, shadows are rendered with a perspective camera
+    Spot { position: Vec3, direction: Vec3, angle: f32, range: f32 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowSettings {
+    pub resolution: i32, // width and height of the shadow map in texels
+    pub bias: f32,       // depth offset that prevents surfaces from shadowing themselves
+    pub pcf_radius: i32, // samples (2r + 1)^2 texels around each fragment to soften edges
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            resolution: 1024,
+            bias: 0.005,
+            pcf_radius: 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub shadows: Option<ShadowSettings>, // None if the light doesn't cast shadows
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, center: Vec3, extent: f32) -> Self {
+        Light {
+            kind: LightKind::Directional { direction: direction.normalize(), center, extent },
+            shadows: Some(ShadowSettings::default()),
+        }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, angle: f32, range: f32) -> Self {
+        Light {
+            kind: LightKind::Spot { position, direction: direction.normalize(), angle, range },
+            shadows: Some(ShadowSettings::default()),
+        }
+    }
+
+    // projection * view of the camera the light's shadow map is rendered from
+    pub fn view_projection(&self) -> Mat4 {
+        match self.kind {
+            LightKind::Directional { direction, center, extent } => {
+                let eye = center - direction * extent * 2.;
+                let view = Mat4::look_at_rh(eye, center, up_vector(direction));
+                let projection = Mat4::orthographic_rh_gl(-extent, extent, -extent, extent, 0.1, extent * 4.);
+                projection * view
+            },
+            LightKind::Spot { position, direction, angle, range } => {
+                let view = Mat4::look_at_rh(position, position + direction, up_vector(direction));
+                let projection = Mat4::perspective_rh_gl(angle * 2., 1., 0.1, range);
+                projection * view
+            },
+        }
+    }
+}
+
+impl Light {
+    // the light as laid out in the Lights uniform block
+    pub fn data(&self) -> LightData {
+        match self.kind {
+            LightKind::Directional { direction, center, extent } => LightData {
+                kind: 0,
+                angle: 0.,
+                range: extent,
+                position: center,
+                direction,
+            },
+            LightKind::Spot { position, direction, angle, range } => LightData {
+                kind: 1,
+                angle,
+                range,
+                position,
+                direction,
+            },
+        }
+    }
+}
+
+// any up vector works for a light camera as long as it isn't parallel to the view direction
+fn up_vector(direction: Vec3) -> Vec3 {
+    if direction.normalize().dot(Vec3::Y).abs() > 0.99 { Vec3::Z } else { Vec3::Y }
+}
@@ -1,8 +1,9 @@
-use std::{collections::HashMap, time::Instant};
+use std::path::Path;
+use std::time::Instant;
 
 use game::Game;
-use sdl2::event::Event;
-use glam::Mat4;
+use sdl2::{event::{Event, WindowEvent}, keyboard::Keycode, pixels::PixelFormatEnum, surface::Surface};
+use glam::{Mat4, Vec2, Vec3};
 
 mod winsdl;
 use winsdl::Winsdl;
@@ -13,105 +14,295 @@ use graphics::*;
 mod dropper;
 use dropper::Dropper;
 
-mod object;
-use object::Object;
-
 mod game;
 
+// entities, components and the systems that run on them every frame
+mod ecs;
+use ecs::World;
+
+mod commands;
+
+mod schedule;
+use schedule::{Schedule, Time};
+
+mod components;
+use components::Camera;
+
+mod scene;
+
+mod physics;
+
+mod input;
+use input::InputState;
+
+mod gamepad;
+use gamepad::Gamepads;
+
+mod replay;
+use replay::{InputSource, LiveInput, Recorder, Replay};
+
+mod random;
+use random::Random;
+
+mod postprocess;
+use postprocess::PostProcessor;
+
+mod light;
+use light::Light;
+
+mod shadow;
+use shadow::ShadowRenderer;
+
+mod assets;
+use assets::Assets;
+
+mod material;
+
+mod preprocessor;
+
+mod uniform_buffer;
+use uniform_buffer::*;
+
+mod renderer;
+use renderer::Renderer;
+
+mod mesh;
+
+mod transform;
+
+mod meshgen;
+
+mod culling;
+use culling::Frustum;
+
+// model loaders, for games to call from initialize
+mod model;
+mod obj;
+mod gltf;
+
+mod json;
+
+mod animation;
+
 fn main() {
     // CREATE WINDOW
     // change so this is game dependent?
-    let width: usize = 600;
-    let height: usize = 600;
+    let mut width: usize = 600;
+    let mut height: usize = 600;
     let mut winsdl = Winsdl::new(width, height).unwrap();
     unsafe { gl::Viewport(0, 0, width as i32, height as i32); }
 
-    // CREATE PROGRAM
-    let program = create_program().unwrap();
-    program.set();
+    // CREATE ASSETS (DEFAULT PROGRAM AND MATERIAL)
+    // --hot-reload loads shaders from the source tree and recompiles them when they're saved
+    let hot_reload = std::env::args().any(|arg| arg == "--hot-reload");
+    let mut assets = Assets::new(hot_reload).unwrap();
 
-    // CREATE UNIFORMS
-    let u_resolution = Uniform::new(program.id(), "u_resolution").unwrap();
-    let u_model_matrix = Uniform::new(program.id(), "u_model_matrix").unwrap();
-    let u_view_matrix = Uniform::new(program.id(), "u_view_matrix").unwrap();
-    let u_projection_matrix = Uniform::new(program.id(), "u_projection_matrix").unwrap();
-    let u_color = Uniform::new(program.id(), "u_color").unwrap();
+    // CHOOSE WHERE INPUT COMES FROM
+    // --replay <file> plays back a recording made with --record <file>, with the same seed and delta times
+    // --fixed-delta <seconds> steps live input by a constant time instead of the clock
+    // --seed <number> seeds the game's random numbers, the seed is printed so any run can be repeated
+    let args: Vec<String> = std::env::args().collect();
+    let option = |name: &str| args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1)).cloned();
+    let fixed_delta = option("--fixed-delta").map(|delta| delta.parse::<f32>().expect("--fixed-delta expects seconds"));
+    let seed = option("--seed").map(|seed| seed.parse::<u64>().expect("--seed expects a number"));
+    let replaying = option("--replay").is_some();
+    let (mut input, seed): (Box<dyn InputSource>, u64) = match option("--replay") {
+        Some(path) => {
+            let replay = Replay::load(Path::new(&path), fixed_delta).unwrap();
+            println!("Replaying {} frames from {}", replay.frames_left(), path);
+            let seed = replay.seed();
+            (Box::new(replay), seed)
+        },
+        None => (Box::new(LiveInput::new(fixed_delta)), seed.unwrap_or_else(rand::random)),
+    };
+    println!("Random seed {}", seed);
+    if let Some(path) = option("--record") {
+        input = Box::new(Recorder::create(input, Path::new(&path), seed).unwrap());
+        println!("Recording input to {}", path);
+    }
 
     // CREATE GAME INSTANCE
-    let mut game: Box<dyn Game> = Box::new(Dropper {
-        objects: HashMap::new(),
-        projection_matrix: Mat4::IDENTITY,
-        view_matrix: Mat4::IDENTITY,
-        screen_width: width as f32,
-        screen_height: height as f32,
-        counter: 0,
-        time_since_drop: 0.0,
-    });
+    let mut game: Box<dyn Game> = Box::new(Dropper);
+    let mut world = World::new();
+    let mut schedule = Schedule::with_engine_systems();
+    world.insert_resource(InputState::new());
+    world.insert_resource(Random::new(seed));
+    // a replay's pad events are for pads that may not be plugged in, so they only reach InputState
+    match winsdl.sdl_context.game_controller() {
+        Ok(_) if replaying => {},
+        Ok(subsystem) => world.insert_resource(Gamepads::new(subsystem)),
+        Err(e) => println!("Couldn't start controller support: {}", e),
+    }
 
     // INITIALIZE GAME
-    game.initialize();
+    game.initialize(&mut world, &mut schedule, &mut assets);
 
     unsafe { 
-        // SET RESOLUTION
-        gl::Uniform2f(u_resolution.id, width as f32, height as f32);
-
         // SET DEPTH HANDLE
         gl::Enable(gl::DEPTH_TEST);
         gl::DepthFunc(gl::LESS);
     }
 
-    let mut last_frame_time = Instant::now();
+    // CREATE POST-PROCESSING CHAIN
+    let mut post = PostProcessor::with_default_effects(width as i32, height as i32, &mut assets).unwrap();
+    let post_toggles = [
+        (Keycode::F1, "bloom"),
+        (Keycode::F2, "tonemap"),
+        (Keycode::F3, "color_grading"),
+        (Keycode::F4, "gamma"),
+        (Keycode::F5, "fxaa"),
+        (Keycode::F6, "vignette"),
+    ];
+    for (key, name) in &post_toggles {
+        let enabled = post.effects().iter().any(|effect| effect.name() == *name && effect.enabled());
+        println!("{}: toggle {} (currently {})", key, name, if enabled { "on" } else { "off" });
+    }
 
-    'running: loop {
-        let delta_time = last_frame_time.elapsed().as_secs_f32();
-        last_frame_time = Instant::now();
+    // CREATE RENDERERS
+    let mut renderer = Renderer::new();
+    let mut shadows = ShadowRenderer::new(&mut assets).unwrap();
 
-        let collisions = detect_collisions(game.get_objects());
-        game.handle_collisions(collisions);
+    // CREATE UNIFORM BUFFERS SHARED BY EVERY PROGRAM
+    let mut camera_buffer = UniformBuffer::<CameraBlock>::new(CAMERA_BINDING);
+    let mut lights_buffer = UniformBuffer::<LightsBlock>::new(LIGHTS_BINDING);
+    let mut time_buffer = UniformBuffer::<TimeBlock>::new(TIME_BINDING);
+    let mut frame_count = 0;
 
-        for event in winsdl.event_pump.poll_iter() {
+    // game time, the sum of the frames' delta times so replays see the same values
+    let mut elapsed = 0.;
+
+    // FRAME STATISTICS, SHOWN IN THE WINDOW TITLE
+    let mut stats_time = Instant::now();
+    let mut stats_frames = 0;
+
+    'running: loop {
+        let frame = input.next_frame(&mut winsdl.event_pump);
+        let delta_time = frame.delta_time;
+        elapsed += delta_time;
+
+        if let Some(input) = world.resource_mut::<InputState>() {
+            input.begin_frame();
+        }
+        for event in frame.events {
             match event {
                 Event::Quit { .. } => break 'running,
-                _ => { game.handle_event(event); },            
+                Event::Window { win_event: WindowEvent::Resized(w, h), .. } => {
+                    width = w as usize;
+                    height = h as usize;
+                    post.resize(w, h).unwrap();
+                },
+                Event::KeyDown { keycode: Some(Keycode::F12), .. } => {
+                    match save_screenshot(post.output(), "screenshot.bmp") {
+                        Ok(()) => println!("Saved screenshot.bmp"),
+                        Err(e) => println!("Couldn't save screenshot: {}", e),
+                    }
+                },
+                Event::KeyDown { keycode: Some(key), .. } if post_toggles.iter().any(|(toggle, _)| *toggle == key) => {
+                    let (_, effect) = post_toggles.iter().find(|(toggle, _)| *toggle == key).unwrap();
+                    if let Some(enabled) = post.toggle(effect) {
+                        println!("{}: {}", effect, if enabled { "on" } else { "off" });
+                    }
+                },
+                _ => {
+                    if let Some(gamepads) = world.resource_mut::<Gamepads>() {
+                        gamepads.handle_event(&event);
+                    }
+                    if let Some(input) = world.resource_mut::<InputState>() {
+                        input.handle_event(&event);
+                    }
+                    game.handle_event(&mut world, event);
+                },            
             }
         }
 
-        game.update(delta_time);
+        // RUN THE GAME'S AND ENGINE'S SYSTEMS
+        world.insert_resource(Time {
+            delta: delta_time,
+            elapsed,
+            frame: frame_count as u64,
+        });
+        schedule.run(&mut world);
+
+        assets.reload_changed_programs();
+
+        // UPDATE PER-FRAME UNIFORM BLOCKS
+        let camera = world.resource::<Camera>().copied().unwrap_or_default();
+        let projection_matrix = camera.projection_matrix;
+        let view_matrix = camera.view_matrix;
+        camera_buffer.update(&CameraBlock {
+            projection_matrix,
+            view_matrix,
+            view_projection_matrix: projection_matrix * view_matrix,
+            position: view_matrix.inverse().w_axis.truncate(),
+            resolution: Vec2::new(width as f32, height as f32),
+        });
+
+        let lights: Vec<Light> = world.query::<Light>().map(|(_, light)| *light).collect();
+        let mut lights_block = LightsBlock { count: lights.len().min(MAX_LIGHTS) as i32, ..Default::default() };
+        for (data, light) in lights_block.lights.iter_mut().zip(&lights) {
+            *data = light.data();
+        }
+        lights_buffer.update(&lights_block);
+
+        time_buffer.update(&TimeBlock {
+            time: elapsed,
+            delta_time,
+            frame: frame_count,
+        });
+        frame_count += 1;
 
         //RENDER
+        let objects = renderer::extract(&world);
+        shadows.render(&lights, &objects, &mut renderer, &assets).unwrap();
+
+        post.begin();
         unsafe {
             // CLEAR W/ BGRD COLOR
             gl::ClearColor(54./255., 159./255., 219./255., 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-            // SET FRUSTUM
-            gl::UniformMatrix4fv(u_projection_matrix.id, 1, gl::FALSE, game.get_projection_matrix().to_cols_array().as_ptr());
-            // SET VIEW
-            gl::UniformMatrix4fv(u_view_matrix.id, 1, gl::FALSE, game.get_view_matrix().to_cols_array().as_ptr());
         }
-    
-        render(game.get_objects(), &u_model_matrix, &u_color);
+
+        // the vertex shader squeezes clip space to keep the scene square, so the visible volume has to match
+        let frustum = Frustum::from_view_projection(&(aspect_correction(width, height) * projection_matrix * view_matrix));
+        renderer.render(&objects, &assets, &shadows, &frustum, &view_matrix);
+
+        post.apply(&assets, elapsed);
+        post.present(width as i32, height as i32);
 
         winsdl.window.gl_swap_window(); // update display
-    }
-}
 
-fn detect_collisions(objects: Vec<(String, &Object)>) -> Vec<(String, String)> {
-    let mut collisions = Vec::new();
-    for i in 0..objects.len() {
-        for j in i + 1..objects.len() {
-            if objects[i].1.intersect(&objects[j].1) {
-                collisions.push((objects[i].0.clone(), objects[j].0.clone()));
-                //println!("Collision detected between {} and {}", objects[i].0, objects[j].0);
-            }
+        stats_frames += 1;
+        if stats_time.elapsed().as_secs_f32() >= 1.0 {
+            let stats = renderer.stats();
+            let fps = stats_frames as f32 / stats_time.elapsed().as_secs_f32();
+            let title = format!("Rust! - {:.0} fps, {} drawn, {} culled, {} draw calls", fps, stats.drawn, stats.culled, stats.draw_calls);
+            winsdl.window.set_title(&title).unwrap();
+            stats_time = Instant::now();
+            stats_frames = 0;
         }
     }
-    collisions
 }
 
-fn render(objects: Vec<(String, &Object)>, u_model_matrix: &Uniform, u_color: &Uniform) {
-    // Render the game objects
-    for (_, object) in objects {
-        object.render(u_model_matrix, u_color);
+// the scaling default.vert applies after projecting, as a matrix
+fn aspect_correction(width: usize, height: usize) -> Mat4 {
+    let (width, height) = (width as f32, height as f32);
+    if width > height {
+        Mat4::from_scale(Vec3::new(height / width, 1., 1.))
+    } else {
+        Mat4::from_scale(Vec3::new(1., width / height, 1.))
     }
-}
\ No newline at end of file
+}
+
+fn save_screenshot(framebuffer: &Framebuffer, path: &str) -> Result<(), String> {
+    let width = framebuffer.width() as usize;
+    let height = framebuffer.height() as usize;
+    let pitch = width * 4;
+    let pixels = framebuffer.read_pixels(0);
+
+    // opengl returns the bottom row first, images expect the top row first
+    let mut flipped: Vec<u8> = pixels.chunks(pitch).rev().flatten().copied().collect();
+
+    // rgba bytes in memory are abgr8888 when read as little-endian u32s
+    let surface = Surface::from_data(&mut flipped, width as u32, height as u32, pitch as u32, PixelFormatEnum::ABGR8888)?;
+    surface.save_bmp(path)
+}
@@ -0,0 +1,136 @@
+use std::rc::Rc;
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::assets::ProgramHandle;
+use crate::graphics::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+// index of a material in Assets, the default handle refers to the engine's default material
+pub struct MaterialHandle(pub(crate) usize);
+
+#[derive(Clone)]
+// every uniform type a game's shaders might take, the built-in materials only use some
+#[allow(dead_code)]
+pub enum MaterialParam {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Mat4(Mat4),
+    Texture(Rc<Texture>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,    // source over destination, weighted by source alpha
+    Additive, // source added to destination, weighted by source alpha
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+// a shader program plus the uniform values and render state it's drawn with
+#[derive(Clone)]
+pub struct Material {
+    pub program: ProgramHandle,
+    params: Vec<(String, MaterialParam)>,
+    pub blend_mode: BlendMode,
+    pub cull_mode: CullMode,
+    pub depth_write: bool,
+}
+
+impl Material {
+    pub fn new(program: ProgramHandle) -> Self {
+        Material {
+            program,
+            params: Vec::new(),
+            blend_mode: BlendMode::Opaque,
+            cull_mode: CullMode::None,
+            depth_write: true,
+        }
+    }
+
+    // a material for the default program with a flat color
+    pub fn colored(program: ProgramHandle, color: Vec3) -> Self {
+        let mut material = Material::new(program);
+        material.set("u_color", MaterialParam::Vec3(color));
+        material
+    }
+
+    pub fn set(&mut self, name: &str, value: MaterialParam) {
+        match self.params.iter_mut().find(|(existing, _)| existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.params.push((name.to_string(), value)),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self, name: &str) -> Option<&MaterialParam> {
+        self.params.iter().find(|(existing, _)| existing == name).map(|(_, value)| value)
+    }
+
+    // applies render state and uploads every parameter to `program`, which must already be in use
+    pub fn apply(&self, program: &Program) {
+        unsafe {
+            match self.blend_mode {
+                BlendMode::Opaque => gl::Disable(gl::BLEND),
+                BlendMode::Alpha => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                },
+                BlendMode::Additive => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+                },
+            }
+
+            match self.cull_mode {
+                CullMode::None => gl::Disable(gl::CULL_FACE),
+                CullMode::Back => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(gl::BACK);
+                },
+                CullMode::Front => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(gl::FRONT);
+                },
+            }
+
+            gl::DepthMask(if self.depth_write { gl::TRUE } else { gl::FALSE });
+        }
+
+        let mut texture_unit = 0;
+        for (name, value) in &self.params {
+            match value {
+                MaterialParam::Int(v) => program.set_int(name, *v),
+                MaterialParam::Float(v) => program.set_float(name, *v),
+                MaterialParam::Vec2(v) => program.set_vec2(name, *v),
+                MaterialParam::Vec3(v) => program.set_vec3(name, *v),
+                MaterialParam::Vec4(v) => program.set_vec4(name, *v),
+                MaterialParam::Mat4(v) => program.set_mat4(name, v),
+                MaterialParam::Texture(texture) => {
+                    program.set_texture(name, texture_unit, texture);
+                    texture_unit += 1;
+                },
+            }
+        }
+    }
+}
+
+// restores the render state materials may change, so passes that don't use materials start from a known state
+pub fn reset_render_state() {
+    unsafe {
+        gl::Disable(gl::BLEND);
+        gl::Disable(gl::CULL_FACE);
+        gl::DepthMask(gl::TRUE);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashMap, rc::Rc};
+
+use glam::{Vec2, Vec3};
+
+use crate::culling::Aabb;
+use crate::graphics::*;
+
+// indexed triangles on the cpu, counter-clockwise when seen from the front
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn new() -> Self {
+        MeshData::default()
+    }
+
+    // appends a vertex, returning its index
+    pub fn push_vertex(&mut self, position: Vec3, normal: Vec3, uv: Vec2) -> u32 {
+        self.vertices.push(Vertex { position, normal, uv, ..Default::default() });
+        self.vertices.len() as u32 - 1
+    }
+
+    pub fn push_triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend([a, b, c]);
+    }
+
+    // replaces the normal of every vertex flagged in `replace` with the area-weighted average of its faces
+    pub fn compute_normals(&mut self, replace: &[bool]) {
+        for (vertex, replace) in self.vertices.iter_mut().zip(replace) {
+            if *replace {
+                vertex.normal = Vec3::ZERO;
+            }
+        }
+        for triangle in self.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize].position);
+            let face_normal = (b - a).cross(c - a);
+            for &index in triangle {
+                if replace[index as usize] {
+                    self.vertices[index as usize].normal += face_normal;
+                }
+            }
+        }
+        for (vertex, replace) in self.vertices.iter_mut().zip(replace) {
+            if *replace {
+                vertex.normal = vertex.normal.try_normalize().unwrap_or(Vec3::Y);
+            }
+        }
+    }
+}
+
+// geometry uploaded to the gpu once and shared by every object drawing it
+// the buffers are only held so they're deleted with the mesh, the vao refers to them
+pub struct Mesh {
+    _vbo: Vbo,
+    vao: Vao,
+    _ibo: Ibo,
+    index_count: i32,
+    bounds: Aabb, // in model space, for culling and collisions
+}
+
+impl Mesh {
+    pub fn new(data: &MeshData) -> Self {
+        let vbo = Vbo::gen();
+        vbo.set(&data.vertices);
+
+        let vao = Vao::gen();
+        vao.set();
+
+        let ibo = Ibo::gen();
+        ibo.set(&data.indices);
+
+        vao.unbind();
+
+        Mesh {
+            _vbo: vbo,
+            vao,
+            _ibo: ibo,
+            index_count: data.indices.len() as i32,
+            bounds: Aabb::from_points(data.vertices.iter().map(|vertex| &vertex.position)),
+        }
+    }
+
+    pub fn vao(&self) -> &Vao {
+        &self.vao
+    }
+
+    pub fn index_count(&self) -> i32 {
+        self.index_count
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+#[derive(Clone)]
+// a reference-counted mesh, its gpu buffers are freed when the last handle is dropped
+pub struct MeshHandle(Rc<Mesh>);
+
+impl MeshHandle {
+    pub fn new(mesh: Mesh) -> Self {
+        MeshHandle(Rc::new(mesh))
+    }
+
+    // identifies the mesh, equal for handles to the same mesh
+    pub fn id(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+}
+
+impl std::ops::Deref for MeshHandle {
+    type Target = Mesh;
+
+    fn deref(&self) -> &Mesh {
+        &self.0
+    }
+}
+
+impl PartialEq for MeshHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for MeshHandle {}
+
+// meshes by name, kept uploaded while no object uses them so spawning another one doesn't upload it again
+// a mesh is only freed once it's removed from the cache and its last handle is dropped
+pub struct MeshCache {
+    meshes: HashMap<String, MeshHandle>,
+}
+
+impl MeshCache {
+    pub fn new() -> Self {
+        MeshCache { meshes: HashMap::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<MeshHandle> {
+        self.meshes.get(name).cloned()
+    }
+
+    // returns the mesh cached under `name`, or creates it from the geometry `create` returns
+    pub fn get_or_create(&mut self, name: &str, create: impl FnOnce() -> MeshData) -> MeshHandle {
+        if let Some(mesh) = self.get(name) {
+            return mesh;
+        }
+        self.insert(name, Mesh::new(&create()))
+    }
+
+    // caches `mesh` under `name`, replacing any previous mesh for new lookups
+    pub fn insert(&mut self, name: &str, mesh: Mesh) -> MeshHandle {
+        let handle = MeshHandle::new(mesh);
+        self.meshes.insert(name.to_string(), handle.clone());
+        handle
+    }
+
+    // forgets the mesh, which is freed once the objects still using it are gone
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.meshes.remove(name).is_some()
+    }
+
+    // frees every mesh nothing but the cache holds, like between levels
+    pub fn clear_unused(&mut self) {
+        self.meshes.retain(|_, mesh| Rc::strong_count(&mesh.0) > 1);
+    }
+}
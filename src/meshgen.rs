@@ -0,0 +1,371 @@
+use std::{collections::HashMap, f32::consts::{PI, TAU}};
+
+use glam::{Vec2, Vec3};
+
+use crate::mesh::MeshData;
+
+// procedural meshes centered on the origin with y up, wound counter-clockwise when seen from outside
+
+// a grid of (u_subdivisions + 1) * (v_subdivisions + 1) vertices spanning `origin` to `origin + u + v`,
+// facing along u x v
+fn push_grid(data: &mut MeshData, origin: Vec3, u: Vec3, v: Vec3, u_subdivisions: u32, v_subdivisions: u32) {
+    let normal = u.cross(v).normalize();
+    let first = data.vertices.len() as u32;
+    let row = u_subdivisions + 1;
+
+    for j in 0..=v_subdivisions {
+        for i in 0..=u_subdivisions {
+            let uv = Vec2::new(i as f32 / u_subdivisions as f32, j as f32 / v_subdivisions as f32);
+            data.push_vertex(origin + u * uv.x + v * uv.y, normal, uv);
+        }
+    }
+
+    for j in 0..v_subdivisions {
+        for i in 0..u_subdivisions {
+            let a = first + j * row + i;
+            let b = a + 1;
+            let c = b + row;
+            let d = a + row;
+            data.push_triangle(a, b, c);
+            data.push_triangle(c, d, a);
+        }
+    }
+}
+
+// a `width` by `height` rectangle in the xy plane, facing +z
+#[allow(dead_code)]
+pub fn quad(width: f32, height: f32) -> MeshData {
+    let mut data = MeshData::new();
+    let origin = Vec3::new(-width / 2., -height / 2., 0.);
+    push_grid(&mut data, origin, Vec3::X * width, Vec3::Y * height, 1, 1);
+    data
+}
+
+// a `width` by `depth` rectangle in the xz plane facing +y, split into `subdivisions` cells along each side
+pub fn grid(width: f32, depth: f32, subdivisions: u32) -> MeshData {
+    let subdivisions = subdivisions.max(1);
+    let mut data = MeshData::new();
+    let origin = Vec3::new(-width / 2., 0., depth / 2.);
+    push_grid(&mut data, origin, Vec3::X * width, Vec3::NEG_Z * depth, subdivisions, subdivisions);
+    data
+}
+
+// a `width` by `depth` rectangle in the xz plane facing +y
+pub fn plane(width: f32, depth: f32) -> MeshData {
+    grid(width, depth, 1)
+}
+
+// a box of `size`, each face split into `subdivisions` cells along each side
+pub fn cube(size: Vec3, subdivisions: u32) -> MeshData {
+    let subdivisions = subdivisions.max(1);
+    let half = size / 2.;
+    let mut data = MeshData::new();
+
+    // normal, then two axes whose cross product is the normal
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+        (Vec3::Z, Vec3::X, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y),
+    ];
+    for (normal, u, v) in faces {
+        let (u, v) = (u * size, v * size);
+        let origin = normal * half - u / 2. - v / 2.;
+        push_grid(&mut data, origin, u, v, subdivisions, subdivisions);
+    }
+    data
+}
+
+// the unit direction at polar angle `theta` from +y and azimuth `phi` counter-clockwise from +x seen from above
+fn spherical(theta: f32, phi: f32) -> Vec3 {
+    Vec3::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin())
+}
+
+// rings of `segments` + 1 vertices around y, at each (polar angle, y offset) in `rows` from top to bottom,
+// joined into a surface of revolution whose first and last rows are poles
+fn push_revolved(data: &mut MeshData, radius: f32, rows: &[(f32, f32)], segments: u32) {
+    let first = data.vertices.len() as u32;
+    let row = segments + 1;
+    let top = rows[0].1 + radius;
+    let length = top - (rows[rows.len() - 1].1 - radius);
+
+    for &(theta, offset) in rows {
+        for s in 0..=segments {
+            let normal = spherical(theta, TAU * s as f32 / segments as f32);
+            let position = normal * radius + Vec3::Y * offset;
+            let uv = Vec2::new(s as f32 / segments as f32, (position.y - top) / length + 1.);
+            data.push_vertex(position, normal, uv);
+        }
+    }
+
+    let last = rows.len() as u32 - 1;
+    for r in 0..last {
+        for s in 0..segments {
+            let a = first + r * row + s;
+            let b = a + row;
+            let c = b + 1;
+            let d = a + 1;
+            // triangles touching a pole would be degenerate
+            if r != 0 {
+                data.push_triangle(a, b, d);
+            }
+            if r + 1 != last {
+                data.push_triangle(d, b, c);
+            }
+        }
+    }
+}
+
+// a sphere of `segments` slices around y and `rings` stacks from pole to pole
+#[allow(dead_code)]
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let rows: Vec<(f32, f32)> = (0..=rings).map(|r| (PI * r as f32 / rings as f32, 0.)).collect();
+    let mut data = MeshData::new();
+    push_revolved(&mut data, radius, &rows, segments);
+    data
+}
+
+// a cylinder of `height` capped with hemispheres of `radius`, `rings` stacks per hemisphere
+#[allow(dead_code)]
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let top = (0..=rings).map(|r| (PI / 2. * r as f32 / rings as f32, height / 2.));
+    let bottom = (0..=rings).map(|r| (PI / 2. * (1. + r as f32 / rings as f32), -height / 2.));
+    let rows: Vec<(f32, f32)> = top.chain(bottom).collect();
+    let mut data = MeshData::new();
+    push_revolved(&mut data, radius, &rows, segments);
+    data
+}
+
+// an icosahedron subdivided `subdivisions` times, more even than a uv sphere
+// the texture coordinates are a spherical projection, which stretches near the poles and wraps at the seam
+#[allow(dead_code)]
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1. + 5f32.sqrt()) / 2.;
+    let mut positions: Vec<Vec3> = [
+        (-1., t, 0.), (1., t, 0.), (-1., -t, 0.), (1., -t, 0.),
+        (0., -1., t), (0., 1., t), (0., -1., -t), (0., 1., -t),
+        (t, 0., -1.), (t, 0., 1.), (-t, 0., -1.), (-t, 0., 1.),
+    ].iter().map(|&(x, y, z)| Vec3::new(x, y, z).normalize()).collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // edges are shared by two triangles, which must share the vertex splitting them
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push(((positions[a as usize] + positions[b as usize]) / 2.).normalize());
+                positions.len() as u32 - 1
+            })
+        };
+
+        triangles = triangles.iter().flat_map(|&[a, b, c]| {
+            let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+            [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+        }).collect();
+    }
+
+    let mut data = MeshData::new();
+    for normal in positions {
+        let uv = Vec2::new(0.5 + (-normal.z).atan2(normal.x) / TAU, 0.5 + normal.y.asin() / PI);
+        data.push_vertex(normal * radius, normal, uv);
+    }
+    for [a, b, c] in triangles {
+        data.push_triangle(a, b, c);
+    }
+    data
+}
+
+// the side of a cone cut at `height`, with `bottom_radius` and `top_radius`, without caps
+fn push_frustum_side(data: &mut MeshData, bottom_radius: f32, top_radius: f32, height: f32, segments: u32) {
+    let first = data.vertices.len() as u32;
+    let row = segments + 1;
+    let slope = Vec2::new(height, bottom_radius - top_radius).normalize();
+
+    for (radius, y, v) in [(bottom_radius, -height / 2., 0.), (top_radius, height / 2., 1.)] {
+        for s in 0..=segments {
+            let direction = spherical(PI / 2., TAU * s as f32 / segments as f32);
+            let normal = direction * slope.x + Vec3::Y * slope.y;
+            data.push_vertex(direction * radius + Vec3::Y * y, normal, Vec2::new(s as f32 / segments as f32, v));
+        }
+    }
+
+    for s in 0..segments {
+        let a = first + s;
+        let b = a + 1;
+        let c = b + row;
+        let d = a + row;
+        data.push_triangle(a, b, c);
+        // a pointed top would make the second triangle degenerate
+        if top_radius > 0. {
+            data.push_triangle(c, d, a);
+        }
+    }
+}
+
+// a disc at `y` facing +y if `up`, or -y otherwise
+fn push_cap(data: &mut MeshData, radius: f32, y: f32, up: bool, segments: u32) {
+    let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+    let center = data.push_vertex(Vec3::Y * y, normal, Vec2::splat(0.5));
+
+    for s in 0..=segments {
+        let direction = spherical(PI / 2., TAU * s as f32 / segments as f32);
+        // mirrored on the bottom so the texture isn't flipped when seen from below
+        let uv = Vec2::new(direction.x, if up { -direction.z } else { direction.z }) / 2. + 0.5;
+        data.push_vertex(direction * radius + Vec3::Y * y, normal, uv);
+    }
+
+    for s in 0..segments {
+        let (a, b) = (center + 1 + s, center + 2 + s);
+        if up {
+            data.push_triangle(center, a, b);
+        } else {
+            data.push_triangle(center, b, a);
+        }
+    }
+}
+
+// a capped cylinder of `segments` sides around y
+#[allow(dead_code)]
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let mut data = MeshData::new();
+    push_frustum_side(&mut data, radius, radius, height, segments);
+    push_cap(&mut data, radius, height / 2., true, segments);
+    push_cap(&mut data, radius, -height / 2., false, segments);
+    data
+}
+
+// a cone pointing up with a capped base of `segments` sides
+#[allow(dead_code)]
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let mut data = MeshData::new();
+    push_frustum_side(&mut data, radius, 0., height, segments);
+    push_cap(&mut data, radius, -height / 2., false, segments);
+    data
+}
+
+// a ring around y of `major_radius`, with a tube of `minor_radius`
+#[allow(dead_code)]
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> MeshData {
+    let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+    let mut data = MeshData::new();
+    let row = minor_segments + 1;
+
+    for i in 0..=major_segments {
+        let phi = TAU * i as f32 / major_segments as f32;
+        let direction = spherical(PI / 2., phi);
+        for j in 0..=minor_segments {
+            let psi = TAU * j as f32 / minor_segments as f32;
+            let normal = direction * psi.cos() + Vec3::Y * psi.sin();
+            let uv = Vec2::new(i as f32 / major_segments as f32, j as f32 / minor_segments as f32);
+            data.push_vertex(direction * major_radius + normal * minor_radius, normal, uv);
+        }
+    }
+
+    for i in 0..major_segments {
+        for j in 0..minor_segments {
+            let a = i * row + j;
+            let b = a + row;
+            let c = b + 1;
+            let d = a + 1;
+            data.push_triangle(a, b, c);
+            data.push_triangle(c, d, a);
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated() -> Vec<(&'static str, MeshData)> {
+        vec![
+            ("quad", quad(2., 1.)),
+            ("grid", grid(4., 3., 5)),
+            ("cube", cube(Vec3::new(1., 2., 3.), 3)),
+            ("uv_sphere", uv_sphere(1.5, 16, 8)),
+            ("icosphere", icosphere(1., 2)),
+            ("cylinder", cylinder(0.5, 2., 12)),
+            ("cone", cone(0.5, 2., 12)),
+            ("capsule", capsule(0.5, 1., 12, 4)),
+            ("torus", torus(2., 0.5, 24, 12)),
+        ]
+    }
+
+    #[test]
+    fn indices_are_in_range() {
+        for (name, data) in generated() {
+            assert!(!data.indices.is_empty(), "{} has no triangles", name);
+            assert_eq!(data.indices.len() % 3, 0, "{} has a partial triangle", name);
+            for &index in &data.indices {
+                assert!((index as usize) < data.vertices.len(), "{} indexes vertex {} of {}", name, index, data.vertices.len());
+            }
+        }
+    }
+
+    #[test]
+    fn triangles_wind_counter_clockwise_around_normals() {
+        for (name, data) in generated() {
+            for triangle in data.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| data.vertices[triangle[i] as usize]);
+                let face_normal = (b.position - a.position).cross(c.position - a.position);
+                assert!(face_normal.length() > 1e-6, "{} has a degenerate triangle {:?}", name, triangle);
+
+                let vertex_normal = a.normal + b.normal + c.normal;
+                assert!(face_normal.dot(vertex_normal) > 0., "{} triangle {:?} is wound clockwise", name, triangle);
+            }
+        }
+    }
+
+    #[test]
+    fn closed_meshes_face_outwards() {
+        for (name, data) in generated() {
+            if name == "quad" || name == "grid" || name == "torus" {
+                continue; // open, or not star-shaped around the origin
+            }
+            for triangle in data.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| data.vertices[triangle[i] as usize].position);
+                let face_normal = (b - a).cross(c - a);
+                assert!(face_normal.dot(a + b + c) > 0., "{} triangle {:?} faces inwards", name, triangle);
+            }
+        }
+    }
+
+    #[test]
+    fn normals_are_unit_length_and_uvs_normalized() {
+        for (name, data) in generated() {
+            for vertex in &data.vertices {
+                assert!((vertex.normal.length() - 1.).abs() < 1e-4, "{} has normal {}", name, vertex.normal);
+                assert!(vertex.uv.cmpge(Vec2::ZERO).all() && vertex.uv.cmple(Vec2::ONE).all(), "{} has uv {}", name, vertex.uv);
+            }
+        }
+    }
+
+    #[test]
+    fn sizes_match_parameters() {
+        let bounds = |data: &MeshData| {
+            let positions = data.vertices.iter().map(|vertex| vertex.position);
+            (positions.clone().fold(Vec3::MAX, Vec3::min), positions.fold(Vec3::MIN, Vec3::max))
+        };
+
+        assert_eq!(bounds(&cube(Vec3::new(1., 2., 3.), 2)), (Vec3::new(-0.5, -1., -1.5), Vec3::new(0.5, 1., 1.5)));
+        assert_eq!(bounds(&plane(2., 4.)), (Vec3::new(-1., 0., -2.), Vec3::new(1., 0., 2.)));
+
+        let (min, max) = bounds(&capsule(0.5, 1., 8, 4));
+        assert!((min.y + 1.).abs() < 1e-5 && (max.y - 1.).abs() < 1e-5);
+
+        assert_eq!(grid(1., 1., 4).vertices.len(), 25);
+        assert_eq!(grid(1., 1., 4).indices.len(), 4 * 4 * 6);
+    }
+}
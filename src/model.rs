@@ -0,0 +1,69 @@
+use std::rc::Rc;
+
+use glam::{Mat4, Vec4};
+
+use crate::animation::{AnimationClip, AnimationPlayer, Skeleton};
+use crate::components::{Color, Name};
+use crate::ecs::{Entity, World};
+use crate::material::MaterialHandle;
+use crate::mesh::MeshHandle;
+use crate::transform::Transform;
+
+// a point in a loaded model's hierarchy, that moves the nodes and parts attached to it
+pub struct ModelNode {
+    pub name: String,
+    pub transform: Mat4,       // relative to the parent node, or the model for nodes without one
+    pub parent: Option<usize>, // index in Model::nodes, always before this node
+}
+
+// a mesh and material of a loaded model, drawn as one entity
+pub struct ModelPart {
+    pub name: String,
+    pub mesh: MeshHandle,
+    pub material: MaterialHandle,
+    pub color: Vec4,    // carries the material's opacity, which the default program reads from the entity's Color
+    pub transform: Mat4, // relative to the node, or the model for parts without one
+    pub node: Option<usize>, // index in Model::nodes
+    pub skeleton: Option<Rc<Skeleton>>, // set for skinned meshes, whose entities get an animation player
+}
+
+// the entities a model file describes, loaded into Assets, see obj::load and gltf::load
+pub struct Model {
+    pub nodes: Vec<ModelNode>,
+    pub parts: Vec<ModelPart>,
+    pub animations: Vec<Rc<AnimationClip>>,
+}
+
+impl Model {
+    #[allow(dead_code)]
+    pub fn animation(&self, name: &str) -> Option<&Rc<AnimationClip>> {
+        self.animations.iter().find(|clip| clip.name == name)
+    }
+
+    // spawns an entity at `transform` with the model's nodes and parts below it, returning the root
+    // nodes and parts are named entities parented like in the file, so moving a node moves what's attached to it
+    #[allow(dead_code)]
+    pub fn instantiate(&self, world: &mut World, transform: Transform) -> Entity {
+        let root = world.spawn();
+        world.insert(root, transform);
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let parent = node.parent.map_or(root, |parent| nodes[parent]);
+            let entity = world.spawn_child(parent, Transform::from_matrix(&node.transform));
+            world.insert(entity, Name(node.name.clone()));
+            nodes.push(entity);
+        }
+        for part in &self.parts {
+            let parent = part.node.map_or(root, |node| nodes[node]);
+            let child = world.spawn_child(parent, Transform::from_matrix(&part.transform));
+            world.insert(child, Name(part.name.clone()));
+            world.insert(child, part.mesh.clone());
+            world.insert(child, part.material);
+            world.insert(child, Color(part.color));
+            if let Some(skeleton) = &part.skeleton {
+                world.insert(child, AnimationPlayer::new(skeleton.clone()));
+            }
+        }
+        root
+    }
+}
@@ -0,0 +1,459 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::assets::{Assets, ProgramHandle};
+use crate::material::{BlendMode, Material, MaterialHandle, MaterialParam};
+use crate::mesh::MeshData;
+use crate::model::{Model, ModelPart};
+
+// wavefront .obj models and their .mtl material libraries
+
+#[derive(Clone, Debug, PartialEq)]
+// the parts of an .mtl material the engine can draw
+pub struct MtlMaterial {
+    pub diffuse: Vec3,
+    pub opacity: f32,
+    pub diffuse_texture: Option<String>, // relative to the .mtl file
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Vec3::ONE,
+            opacity: 1.,
+            diffuse_texture: None,
+        }
+    }
+}
+
+// the triangles of one group drawn with one material
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub data: MeshData,
+}
+
+pub struct ObjFile {
+    pub groups: Vec<ObjGroup>,
+    pub material_libraries: Vec<String>, // relative to the .obj file
+}
+
+fn error(file: &str, line: usize, message: impl std::fmt::Display) -> String {
+    format!("{}:{}: {}", file, line, message)
+}
+
+fn parse_floats(args: &[&str], file: &str, line: usize) -> Result<Vec<f32>, String> {
+    args.iter()
+        .map(|arg| arg.parse::<f32>().map_err(|_| error(file, line, format!("expected a number, found \"{}\"", arg))))
+        .collect()
+}
+
+// the first `N` numbers of `args`, which may have `extra` more that are ignored
+fn parse_vector<const N: usize>(args: &[&str], extra: usize, file: &str, line: usize) -> Result<[f32; N], String> {
+    if args.len() < N || args.len() > N + extra {
+        return Err(error(file, line, format!("expected {} to {} numbers, found {}", N, N + extra, args.len())));
+    }
+    let values = parse_floats(args, file, line)?;
+    Ok(std::array::from_fn(|i| values[i]))
+}
+
+// resolves a 1-based or negative (relative to the end) index into a list of `count` elements
+fn resolve_index(index: &str, count: usize, kind: &str, file: &str, line: usize) -> Result<usize, String> {
+    let value: i64 = index.parse().map_err(|_| error(file, line, format!("expected a {} index, found \"{}\"", kind, index)))?;
+    let resolved = match value {
+        1.. => value - 1,
+        ..=-1 => count as i64 + value,
+        0 => return Err(error(file, line, format!("{} indices start at 1", kind))),
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(error(file, line, format!("{} index {} is out of range, there are {}", kind, value, count)));
+    }
+    Ok(resolved as usize)
+}
+
+// position, texture coordinate and normal indices
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+struct GroupBuilder {
+    name: String,
+    material: Option<String>,
+    data: MeshData,
+    vertices: HashMap<VertexKey, u32>,
+    missing_normals: Vec<bool>, // per vertex, for faces without normals
+}
+
+impl GroupBuilder {
+    fn new(name: &str, material: Option<&str>) -> Self {
+        GroupBuilder {
+            name: name.to_string(),
+            material: material.map(str::to_string),
+            data: MeshData::new(),
+            vertices: HashMap::new(),
+            missing_normals: Vec::new(),
+        }
+    }
+
+    fn finish(mut self) -> ObjGroup {
+        if self.missing_normals.contains(&true) {
+            self.data.compute_normals(&self.missing_normals);
+        }
+        ObjGroup {
+            name: self.name,
+            material: self.material,
+            data: self.data,
+        }
+    }
+}
+
+// parses an .obj file, `file` names it in errors
+// faces with more than three vertices are triangulated as fans, so they must be convex
+pub fn parse_obj(source: &str, file: &str) -> Result<ObjFile, String> {
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<Vec2> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut material_libraries = Vec::new();
+
+    let mut groups = vec![GroupBuilder::new("default", None)];
+    let mut current = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                // some exporters append a vertex color, which is ignored
+                let [x, y, z] = parse_vector(&args, 4, file, line_number)?;
+                positions.push(Vec3::new(x, y, z));
+            },
+            "vt" => {
+                // v and w are optional, w is ignored
+                if args.is_empty() || args.len() > 3 {
+                    return Err(error(file, line_number, format!("expected 1 to 3 numbers, found {}", args.len())));
+                }
+                let values = parse_floats(&args, file, line_number)?;
+                uvs.push(Vec2::new(values[0], values.get(1).copied().unwrap_or(0.)));
+            },
+            "vn" => {
+                let [x, y, z] = parse_vector(&args, 0, file, line_number)?;
+                normals.push(Vec3::new(x, y, z).normalize_or_zero());
+            },
+            "g" | "o" | "usemtl" => {
+                let name = args.join(" ");
+                let (group, material) = match keyword {
+                    "usemtl" => {
+                        if name.is_empty() {
+                            return Err(error(file, line_number, "usemtl needs a material name"));
+                        }
+                        (groups[current].name.clone(), Some(name))
+                    },
+                    _ => (if name.is_empty() { "default".to_string() } else { name }, groups[current].material.clone()),
+                };
+
+                // a group and material seen before keep adding to the same mesh
+                current = match groups.iter().position(|builder| builder.name == group && builder.material == material) {
+                    Some(existing) => existing,
+                    None => {
+                        groups.push(GroupBuilder::new(&group, material.as_deref()));
+                        groups.len() - 1
+                    },
+                };
+            },
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error(file, line_number, format!("a face needs at least 3 vertices, found {}", args.len())));
+                }
+
+                let builder = &mut groups[current];
+                let mut face = Vec::with_capacity(args.len());
+                for arg in &args {
+                    // v, v/vt, v//vn or v/vt/vn
+                    let mut parts = arg.split('/');
+                    let position = resolve_index(parts.next().unwrap_or(""), positions.len(), "position", file, line_number)?;
+                    let uv = match parts.next() {
+                        Some("") | None => None,
+                        Some(index) => Some(resolve_index(index, uvs.len(), "texture coordinate", file, line_number)?),
+                    };
+                    let normal = match parts.next() {
+                        Some("") | None => None,
+                        Some(index) => Some(resolve_index(index, normals.len(), "normal", file, line_number)?),
+                    };
+                    if parts.next().is_some() {
+                        return Err(error(file, line_number, format!("malformed face vertex \"{}\"", arg)));
+                    }
+
+                    let key = (position, uv, normal);
+                    let index = match builder.vertices.get(&key) {
+                        Some(index) => *index,
+                        None => {
+                            let index = builder.data.push_vertex(
+                                positions[position],
+                                normal.map_or(Vec3::ZERO, |normal| normals[normal]),
+                                uv.map_or(Vec2::ZERO, |uv| uvs[uv]),
+                            );
+                            builder.missing_normals.push(normal.is_none());
+                            builder.vertices.insert(key, index);
+                            index
+                        },
+                    };
+                    face.push(index);
+                }
+
+                for j in 1..face.len() - 1 {
+                    builder.data.push_triangle(face[0], face[j], face[j + 1]);
+                }
+            },
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(error(file, line_number, "mtllib needs a file name"));
+                }
+                material_libraries.extend(args.iter().map(|arg| arg.to_string()));
+            },
+            // smoothing groups, lines, points and free-form geometry aren't supported
+            _ => {},
+        }
+    }
+
+    Ok(ObjFile {
+        groups: groups.into_iter().filter(|builder| !builder.data.indices.is_empty()).map(GroupBuilder::finish).collect(),
+        material_libraries,
+    })
+}
+
+// parses an .mtl file into its materials by name, `file` names it in errors
+pub fn parse_mtl(source: &str, file: &str) -> Result<HashMap<String, MtlMaterial>, String> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else { continue };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(error(file, line_number, "newmtl needs a material name"));
+            }
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((args.join(" "), MtlMaterial::default()));
+            continue;
+        }
+
+        let is_material_statement = matches!(keyword, "Ka" | "Kd" | "Ks" | "Ke" | "Ns" | "Ni" | "d" | "Tr" | "illum") || keyword.starts_with("map_");
+        let Some((_, material)) = current.as_mut() else {
+            if is_material_statement {
+                return Err(error(file, line_number, format!("{} before any newmtl", keyword)));
+            }
+            continue;
+        };
+
+        match keyword {
+            "Kd" => {
+                let [r, g, b] = parse_vector(&args, 0, file, line_number)?;
+                material.diffuse = Vec3::new(r, g, b);
+            },
+            "d" => {
+                let [d] = parse_vector(&args, 0, file, line_number)?;
+                material.opacity = d.clamp(0., 1.);
+            },
+            "Tr" => {
+                let [transparency] = parse_vector(&args, 0, file, line_number)?;
+                material.opacity = (1. - transparency).clamp(0., 1.);
+            },
+            "map_Kd" => {
+                // options like "-s 1 1 1" come before the file name
+                let Some(texture) = args.last() else {
+                    return Err(error(file, line_number, "map_Kd needs a file name"));
+                };
+                material.diffuse_texture = Some(texture.to_string());
+            },
+            // lighting isn't supported, so only the diffuse color and opacity matter
+            _ => {},
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    Ok(materials)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))
+}
+
+// loads an .obj file with its material libraries and textures into `assets`
+// groups without a material use the default material
+#[allow(dead_code)] // called by games, the dropper doesn't load models
+pub fn load(assets: &mut Assets, path: &Path) -> Result<Model, String> {
+    let file = path.display().to_string();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let obj = parse_obj(&read(path)?, &file)?;
+
+    let mut mtl_materials = HashMap::new();
+    for library in &obj.material_libraries {
+        let library_path = dir.join(library);
+        let parsed = parse_mtl(&read(&library_path)?, &library_path.display().to_string())?;
+        mtl_materials.extend(parsed.into_iter().map(|(name, material)| (name, (material, library_path.clone()))));
+    }
+
+    // materials used by several groups are only created once
+    let mut materials: HashMap<String, (MaterialHandle, f32)> = HashMap::new();
+    let mut parts = Vec::new();
+
+    for group in obj.groups {
+        let (material, opacity) = match &group.material {
+            None => (MaterialHandle::default(), 1.),
+            Some(name) if materials.contains_key(name) => materials[name],
+            Some(name) => {
+                let Some((mtl, library_path)) = mtl_materials.get(name) else {
+                    return Err(format!("{}: group {} uses undefined material {}", file, group.name, name));
+                };
+
+                let mut material = match &mtl.diffuse_texture {
+                    Some(texture) => {
+                        let texture_path = library_path.parent().unwrap_or(Path::new("")).join(texture);
+                        let texture = assets.load_texture(&texture_path)?;
+                        let program = assets.load_program("default.vert", "default.frag", &["DIFFUSE_TEXTURE"])?;
+                        let mut material = Material::colored(program, mtl.diffuse);
+                        material.set("u_diffuse_texture", MaterialParam::Texture(texture));
+                        material
+                    },
+                    None => Material::colored(ProgramHandle::default(), mtl.diffuse),
+                };
+                if mtl.opacity < 1. {
+                    material.blend_mode = BlendMode::Alpha;
+                }
+
+                let handle = assets.add_material(material);
+                materials.insert(name.clone(), (handle, mtl.opacity));
+                (handle, mtl.opacity)
+            },
+        };
+
+        let name = match &group.material {
+            Some(material) => format!("{}.{}", group.name, material),
+            None => group.name,
+        };
+        let data = group.data;
+        let mesh = assets.add_mesh(&format!("{}:{}", file, name), move || data);
+        parts.push(ModelPart {
+            name,
+            mesh,
+            material,
+            color: Vec4::new(1., 1., 1., opacity),
+            transform: Mat4::IDENTITY,
+            node: None,
+            skeleton: None,
+        });
+    }
+
+    Ok(Model { nodes: Vec::new(), parts, animations: Vec::new() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    fn positions(data: &MeshData) -> Vec<Vec3> {
+        data.vertices.iter().map(|vertex| vertex.position).collect()
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_latest_vertex() {
+        let absolute = parse_obj(&format!("{}vn 0 0 1\nf 1//1 2//1 3//1\n", SQUARE), "a.obj").unwrap();
+        let relative = parse_obj(&format!("{}vn 0 0 1\nf -4//-1 -3//-1 -2//-1\n", SQUARE), "r.obj").unwrap();
+        assert_eq!(positions(&absolute.groups[0].data), positions(&relative.groups[0].data));
+        assert_eq!(absolute.groups[0].data.indices, relative.groups[0].data.indices);
+
+        // relative to the vertices read so far, not to the whole file
+        let early = parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nv 5 5 5\n", "e.obj").unwrap();
+        assert_eq!(positions(&early.groups[0].data), vec![Vec3::ZERO, Vec3::X, Vec3::Y]);
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let obj = parse_obj("v 0 0 0\nv 1 0 0\nv 2 1 0\nv 1 2 0\nv 0 1 0\nf 1 2 3 4 5\n", "fan.obj").unwrap();
+        let data = &obj.groups[0].data;
+        assert_eq!(data.vertices.len(), 5);
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    #[test]
+    fn missing_normals_are_computed_from_the_faces() {
+        let obj = parse_obj(&format!("{}vt 0 0\nf 1/1 2/1 3/1 4/1\n", SQUARE), "flat.obj").unwrap();
+        for vertex in &obj.groups[0].data.vertices {
+            assert!(vertex.normal.abs_diff_eq(Vec3::Z, 1e-5), "normal {} should face +z", vertex.normal);
+        }
+
+        // given normals are kept as they are
+        let obj = parse_obj(&format!("{}vn 1 0 0\nf 1//1 2//1 3//1\n", SQUARE), "given.obj").unwrap();
+        assert!(obj.groups[0].data.vertices.iter().all(|vertex| vertex.normal == Vec3::X));
+    }
+
+    #[test]
+    fn groups_and_materials_split_meshes() {
+        let source = format!("mtllib a.mtl b.mtl\n{}usemtl red\nf 1 2 3\ng lid\nf 1 3 4\ng default\nf 2 3 4\n", SQUARE);
+        let obj = parse_obj(&source, "groups.obj").unwrap();
+        assert_eq!(obj.material_libraries, vec!["a.mtl", "b.mtl"]);
+        let groups: Vec<(&str, Option<&str>, usize)> = obj.groups.iter()
+            .map(|group| (group.name.as_str(), group.material.as_deref(), group.data.indices.len()))
+            .collect();
+        assert_eq!(groups, vec![("default", Some("red"), 6), ("lid", Some("red"), 3)]);
+    }
+
+    #[test]
+    fn malformed_obj_lines_are_rejected_with_their_line() {
+        let cases = [
+            ("v 1 2\n", "t.obj:1: expected 3 to 7 numbers, found 2"),
+            ("v 1 x 3\n", "t.obj:1: expected a number, found \"x\""),
+            ("vt\n", "t.obj:1: expected 1 to 3 numbers, found 0"),
+            ("v 0 0 0\nf 1 1\n", "t.obj:2: a face needs at least 3 vertices, found 2"),
+            ("v 0 0 0\nf 1 2 1\n", "t.obj:2: position index 2 is out of range, there are 1"),
+            ("v 0 0 0\nf 0 1 1\n", "t.obj:2: position indices start at 1"),
+            ("v 0 0 0\nf -2 1 1\n", "t.obj:2: position index -2 is out of range, there are 1"),
+            ("v 0 0 0\nf 1/1 1 1\n", "t.obj:2: texture coordinate index 1 is out of range, there are 0"),
+            ("v 0 0 0\nf 1//// 1 1\n", "t.obj:2: malformed face vertex \"1////\""),
+            ("usemtl\n", "t.obj:1: usemtl needs a material name"),
+            ("mtllib # nothing\n", "t.obj:1: mtllib needs a file name"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_obj(source, "t.obj").err().as_deref(), Some(expected), "parsing {:?}", source);
+        }
+    }
+
+    #[test]
+    fn mtl_materials_keep_diffuse_color_opacity_and_texture() {
+        let source = "newmtl red paint\nKd 1 0 0\nd 0.5\nillum 2\nnewmtl glass\nTr 0.75\nmap_Kd -s 2 2 2 glass.png\nnewmtl plain\n";
+        let materials = parse_mtl(source, "t.mtl").unwrap();
+        assert_eq!(materials.len(), 3);
+        assert_eq!(materials["red paint"], MtlMaterial { diffuse: Vec3::X, opacity: 0.5, diffuse_texture: None });
+        assert_eq!(materials["glass"].opacity, 0.25);
+        assert_eq!(materials["glass"].diffuse_texture.as_deref(), Some("glass.png"));
+        assert_eq!(materials["plain"], MtlMaterial::default());
+    }
+
+    #[test]
+    fn malformed_mtl_lines_are_rejected_with_their_line() {
+        let cases = [
+            ("newmtl\n", "t.mtl:1: newmtl needs a material name"),
+            ("Kd 1 1 1\n", "t.mtl:1: Kd before any newmtl"),
+            ("newmtl a\nKd 1 0\n", "t.mtl:2: expected 3 to 3 numbers, found 2"),
+            ("newmtl a\nd opaque\n", "t.mtl:2: expected a number, found \"opaque\""),
+            ("newmtl a\nmap_Kd\n", "t.mtl:2: map_Kd needs a file name"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_mtl(source, "t.mtl").err().as_deref(), Some(expected), "parsing {:?}", source);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use glam::Vec3;
+
+use crate::culling::Aabb;
+use crate::ecs::{Entity, World};
+use crate::scene::GlobalTransform;
+use crate::mesh::MeshHandle;
+use crate::schedule::Time;
+use crate::transform::Transform;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// a box in the entity's local space that detect_collisions tests against other colliders
+pub struct Collider {
+    pub bounds: Aabb,
+}
+
+impl Collider {
+    pub fn from_mesh(mesh: &MeshHandle) -> Self {
+        Collider { bounds: mesh.bounds() }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+// moves the entity's Transform every frame, in units per second
+pub struct RigidBody {
+    pub velocity: Vec3,
+    pub acceleration: Vec3,
+}
+
+#[derive(Clone, Debug, Default)]
+// pairs of entities whose colliders overlapped this frame, each pair listed once
+pub struct Collisions(pub Vec<(Entity, Entity)>);
+
+pub fn integrate_rigid_bodies(world: &mut World) {
+    let delta = world.resource::<Time>().map_or(0., |time| time.delta);
+    for (_, body) in world.query_mut::<RigidBody>() {
+        body.velocity += body.acceleration * delta;
+    }
+    for (_, transform, body) in world.query2_mut::<Transform, RigidBody>() {
+        transform.translate(body.velocity * delta);
+    }
+}
+
+// compares world bounds, exact for entities without rotation
+pub fn detect_collisions(world: &mut World) {
+    let colliders: Vec<(Entity, Aabb)> = world.query2::<Collider, GlobalTransform>()
+        .map(|(entity, collider, global)| (entity, collider.bounds.transformed(&global.0)))
+        .collect();
+
+    let mut collisions = Vec::new();
+    for i in 0..colliders.len() {
+        for j in i + 1..colliders.len() {
+            if colliders[i].1.intersects(&colliders[j].1) {
+                collisions.push((colliders[i].0, colliders[j].0));
+            }
+        }
+    }
+    world.insert_resource(Collisions(collisions));
+}
@@ -0,0 +1,235 @@
+use glam::{Vec2, Vec3};
+
+use crate::assets::{Assets, ProgramHandle};
+use crate::graphics::*;
+
+const FULLSCREEN_VERT: &str = "post/fullscreen.vert";
+
+// format of the intermediate targets, floating point so tone mapping has hdr values to work with
+const TARGET_FORMAT: TextureFormat = TextureFormat::Rgba16F;
+
+#[derive(Clone, Copy, Debug)]
+// int and vec2 are for effects games add, the built-in ones only take floats and vec3s
+#[allow(dead_code)]
+pub enum EffectParam {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+}
+
+// a single full-screen pass
+//
+// user fragment shaders receive the output of the previous pass and should declare:
+//     in vec2 v_uv;
+//     out vec4 Color;
+//     uniform sampler2D u_texture;
+//     uniform vec2 u_resolution; // optional
+//     uniform float u_time;      // optional
+pub struct Effect {
+    name: String,
+    program: ProgramHandle,
+    params: Vec<(String, EffectParam)>,
+    enabled: bool,
+}
+
+impl Effect {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(&self, program: &Program, source: &Texture, time: f32) {
+        program.set();
+        program.set_texture("u_texture", 0, source);
+        if program.has_uniform("u_resolution") {
+            program.set_vec2("u_resolution", Vec2::new(source.width() as f32, source.height() as f32));
+        }
+        if program.has_uniform("u_time") {
+            program.set_float("u_time", time);
+        }
+        for (name, value) in &self.params {
+            match *value {
+                EffectParam::Int(v) => program.set_int(name, v),
+                EffectParam::Float(v) => program.set_float(name, v),
+                EffectParam::Vec2(v) => program.set_vec2(name, v),
+                EffectParam::Vec3(v) => program.set_vec3(name, v),
+            }
+        }
+        unsafe { gl::DrawArrays(gl::TRIANGLES, 0, 3); }
+    }
+}
+
+// renders the scene offscreen and runs it through a chain of full-screen effects in insertion order
+pub struct PostProcessor {
+    scene: Framebuffer,
+    ping_pong: [Framebuffer; 2],
+    quad: Vao,
+    effects: Vec<Effect>,
+    output: usize, // index into ping_pong holding the last result
+    processed: bool, // false if no effect ran and the scene target holds the final image
+}
+
+impl PostProcessor {
+    pub fn new(width: i32, height: i32) -> Result<Self, String> {
+        let scene = Framebuffer::new(
+            width,
+            height,
+            &[AttachmentKind::Texture(TARGET_FORMAT)],
+            Some(AttachmentKind::Renderbuffer(TextureFormat::Depth24Stencil8)),
+        )?;
+        let ping_pong = [
+            Framebuffer::new(width, height, &[AttachmentKind::Texture(TARGET_FORMAT)], None)?,
+            Framebuffer::new(width, height, &[AttachmentKind::Texture(TARGET_FORMAT)], None)?,
+        ];
+
+        Ok(PostProcessor {
+            scene,
+            ping_pong,
+            quad: Vao::gen(),
+            effects: Vec::new(),
+            output: 0,
+            processed: false,
+        })
+    }
+
+    // creates a post processor with the built-in effects, all disabled since scene colors are authored in display space
+    // their shaders are loaded through `assets`, so they're hot reloaded like any other program
+    pub fn with_default_effects(width: i32, height: i32, assets: &mut Assets) -> Result<Self, String> {
+        let mut post = PostProcessor::new(width, height)?;
+
+        post.load_effect(assets, "bloom", "post/bloom.frag")?;
+        post.set_param("bloom", "u_threshold", EffectParam::Float(0.8));
+        post.set_param("bloom", "u_intensity", EffectParam::Float(1.0));
+        post.set_param("bloom", "u_radius", EffectParam::Float(2.0));
+        post.set_enabled("bloom", false);
+
+        post.load_effect(assets, "tonemap", "post/tonemap.frag")?;
+        post.set_param("tonemap", "u_exposure", EffectParam::Float(1.0));
+        post.set_enabled("tonemap", false);
+
+        post.load_effect(assets, "color_grading", "post/color_grading.frag")?;
+        post.set_param("color_grading", "u_brightness", EffectParam::Float(0.0));
+        post.set_param("color_grading", "u_contrast", EffectParam::Float(1.1));
+        post.set_param("color_grading", "u_saturation", EffectParam::Float(1.2));
+        post.set_param("color_grading", "u_tint", EffectParam::Vec3(Vec3::ONE));
+        post.set_enabled("color_grading", false);
+
+        post.load_effect(assets, "gamma", "post/gamma.frag")?;
+        post.set_param("gamma", "u_gamma", EffectParam::Float(2.2));
+        post.set_enabled("gamma", false);
+
+        post.load_effect(assets, "fxaa", "post/fxaa.frag")?;
+        post.set_enabled("fxaa", false);
+
+        post.load_effect(assets, "vignette", "post/vignette.frag")?;
+        post.set_param("vignette", "u_strength", EffectParam::Float(0.6));
+        post.set_param("vignette", "u_softness", EffectParam::Float(0.5));
+        post.set_enabled("vignette", false);
+
+        Ok(post)
+    }
+
+    // loads a fragment shader from the shader directory and appends it to the end of the chain, enabled
+    pub fn load_effect(&mut self, assets: &mut Assets, name: &str, frag: &str) -> Result<(), String> {
+        let program = assets.load_program(FULLSCREEN_VERT, frag, &[])
+            .map_err(|e| format!("Couldn't compile effect {}: {}", name, e))?;
+        self.push_effect(name, program);
+        Ok(())
+    }
+
+    // compiles a user fragment shader from memory and appends it to the end of the chain, enabled
+    // unlike load_effect, there's no file to hot reload it from
+    #[allow(dead_code)]
+    pub fn add_effect(&mut self, assets: &mut Assets, name: &str, frag_source: &str) -> Result<(), String> {
+        let vert_source = assets.shader_source(FULLSCREEN_VERT)?;
+        let program = Program::from_sources(&vert_source, frag_source)
+            .map_err(|e| format!("Couldn't compile effect {}: {}", name, e))?;
+        let program = assets.add_program(program);
+        self.push_effect(name, program);
+        Ok(())
+    }
+
+    fn push_effect(&mut self, name: &str, program: ProgramHandle) {
+        self.effects.retain(|effect| effect.name != name);
+        self.effects.push(Effect {
+            name: name.to_string(),
+            program,
+            params: Vec::new(),
+            enabled: true,
+        });
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if let Some(effect) = self.effects.iter_mut().find(|effect| effect.name == name) {
+            effect.enabled = enabled;
+        }
+    }
+
+    // flips an effect on or off, returning its new state
+    pub fn toggle(&mut self, name: &str) -> Option<bool> {
+        let effect = self.effects.iter_mut().find(|effect| effect.name == name)?;
+        effect.enabled = !effect.enabled;
+        Some(effect.enabled)
+    }
+
+    pub fn set_param(&mut self, name: &str, param: &str, value: EffectParam) {
+        if let Some(effect) = self.effects.iter_mut().find(|effect| effect.name == name) {
+            match effect.params.iter_mut().find(|(existing, _)| existing == param) {
+                Some((_, existing)) => *existing = value,
+                None => effect.params.push((param.to_string(), value)),
+            }
+        }
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), String> {
+        self.scene.resize(width, height)?;
+        for target in self.ping_pong.iter_mut() {
+            target.resize(width, height)?;
+        }
+        Ok(())
+    }
+
+    // binds the offscreen target the scene should be rendered into
+    pub fn begin(&self) {
+        self.scene.bind();
+    }
+
+    // runs every enabled effect over the rendered scene
+    pub fn apply(&mut self, assets: &Assets, time: f32) {
+        self.processed = false;
+
+        unsafe { gl::Disable(gl::DEPTH_TEST); }
+        self.quad.bind();
+
+        let mut target = 0;
+        for effect in self.effects.iter().filter(|effect| effect.enabled) {
+            let source = if self.processed { &self.ping_pong[1 - target] } else { &self.scene };
+            self.ping_pong[target].bind();
+            effect.apply(assets.program(effect.program), source.color_texture(0).unwrap(), time);
+            self.output = target;
+            self.processed = true;
+            target = 1 - target;
+        }
+
+        self.quad.unbind();
+        unsafe { gl::Enable(gl::DEPTH_TEST); }
+    }
+
+    // the framebuffer holding the final image of the last `apply`
+    pub fn output(&self) -> &Framebuffer {
+        if self.processed { &self.ping_pong[self.output] } else { &self.scene }
+    }
+
+    // copies the final image to the window
+    pub fn present(&self, width: i32, height: i32) {
+        self.output().blit_to_default(0, width, height);
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+use crate::graphics::*;
+
+// shaders live here in the source tree, and are read from it at runtime when hot reloading
+pub const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders");
+
+// shaders baked into the binary, resolved by the same names as the files in SHADER_DIR
+const EMBEDDED_SHADERS: &[(&str, &str)] = &[
+    ("common.glsl", include_str!("shaders/common.glsl")),
+    ("instancing.glsl", include_str!("shaders/instancing.glsl")),
+    ("shadows.glsl", include_str!("shaders/shadows.glsl")),
+    ("skinning.glsl", include_str!("shaders/skinning.glsl")),
+    ("default.vert", include_str!("shaders/default.vert")),
+    ("default.frag", include_str!("shaders/default.frag")),
+    ("depth.vert", include_str!("shaders/depth.vert")),
+    ("depth.frag", include_str!("shaders/depth.frag")),
+    ("post/fullscreen.vert", include_str!("shaders/post/fullscreen.vert")),
+    ("post/bloom.frag", include_str!("shaders/post/bloom.frag")),
+    ("post/tonemap.frag", include_str!("shaders/post/tonemap.frag")),
+    ("post/color_grading.frag", include_str!("shaders/post/color_grading.frag")),
+    ("post/gamma.frag", include_str!("shaders/post/gamma.frag")),
+    ("post/fxaa.frag", include_str!("shaders/post/fxaa.frag")),
+    ("post/vignette.frag", include_str!("shaders/post/vignette.frag")),
+];
+
+// a shader with its includes expanded, and the files it was assembled from
+pub struct PreprocessedSource {
+    pub source: String,
+    pub files: Vec<String>, // indexed by the source string number in #line directives
+}
+
+impl PreprocessedSource {
+    // rewrites "<file number>:<line>" and "<file number>(<line>)" locations in a compile log to "<file name>:<line>"
+    pub fn map_log(&self, log: &str) -> String {
+        log.lines().map(|line| self.map_log_line(line)).collect::<Vec<_>>().join("\n")
+    }
+
+    fn map_log_line(&self, line: &str) -> String {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let starts_number = bytes[i].is_ascii_digit() && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric());
+            if !starts_number {
+                i += 1;
+                continue;
+            }
+
+            let file_start = i;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            let file_end = i;
+            if i == bytes.len() || (bytes[i] != b':' && bytes[i] != b'(') {
+                continue;
+            }
+
+            let separator = bytes[i];
+            let line_start = i + 1;
+            let mut line_end = line_start;
+            while line_end < bytes.len() && bytes[line_end].is_ascii_digit() {
+                line_end += 1;
+            }
+            if line_end == line_start || (separator == b'(' && bytes.get(line_end) != Some(&b')')) {
+                continue;
+            }
+            let rest = if separator == b'(' { line_end + 1 } else { line_end };
+
+            let file: usize = line[file_start..file_end].parse().unwrap_or(usize::MAX);
+            if let Some(name) = self.files.get(file) {
+                return format!("{}{}:{}{}", &line[..file_start], name, &line[line_start..line_end], &line[rest..]);
+            }
+        }
+        line.to_string()
+    }
+}
+
+// resolves #include "file" directives and injects #defines ahead of compiling
+pub struct ShaderPreprocessor {
+    dir: Option<PathBuf>, // read shaders from disk when set, otherwise from the embedded sources
+    sources: HashMap<String, String>,
+}
+
+impl ShaderPreprocessor {
+    // resolves shaders from the sources baked into the binary
+    pub fn embedded() -> Self {
+        ShaderPreprocessor {
+            dir: None,
+            sources: EMBEDDED_SHADERS.iter().map(|(name, source)| (name.to_string(), source.to_string())).collect(),
+        }
+    }
+
+    // resolves shaders from files in `dir`
+    pub fn from_dir(dir: &Path) -> Self {
+        ShaderPreprocessor {
+            dir: Some(dir.to_path_buf()),
+            sources: HashMap::new(),
+        }
+    }
+
+    // where `name` is read from on disk, if it isn't embedded
+    pub fn path(&self, name: &str) -> Option<PathBuf> {
+        if self.sources.contains_key(name) {
+            return None;
+        }
+        self.dir.as_ref().map(|dir| dir.join(name))
+    }
+
+    fn read(&self, name: &str) -> Result<String, String> {
+        if let Some(source) = self.sources.get(name) {
+            return Ok(source.clone());
+        }
+        match self.path(name) {
+            Some(path) => fs::read_to_string(&path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e)),
+            None => Err(format!("Couldn't find shader {}", name)),
+        }
+    }
+
+    // expands includes in `name` and defines each entry of `defines` ("NAME" or "NAME VALUE") after its #version line
+    pub fn preprocess(&self, name: &str, defines: &[&str]) -> Result<PreprocessedSource, String> {
+        let mut preprocessed = PreprocessedSource {
+            source: String::new(),
+            files: Vec::new(),
+        };
+        self.expand(name, defines, &mut preprocessed)?;
+        Ok(preprocessed)
+    }
+
+    fn expand(&self, name: &str, defines: &[&str], out: &mut PreprocessedSource) -> Result<(), String> {
+        let source = self.read(name)?;
+        let file = out.files.len();
+        out.files.push(name.to_string());
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with("#version") {
+                if file == 0 {
+                    out.source.push_str(line);
+                    out.source.push('\n');
+                    for define in defines {
+                        out.source.push_str(&format!("#define {}\n", define));
+                    }
+                    out.source.push_str(&format!("#line {} {}\n", line_number + 1, file));
+                } else {
+                    out.source.push('\n'); // only the top-level shader may declare a version
+                }
+            } else if let Some(directive) = trimmed.strip_prefix("#include") {
+                let included = directive.trim();
+                if included.len() < 2 || !included.starts_with('"') || !included.ends_with('"') {
+                    return Err(format!("{}:{}: expected #include \"file\"", name, line_number));
+                }
+                let included = &included[1..included.len() - 1];
+
+                // every file is included at most once, which also breaks include cycles
+                if !out.files.iter().any(|existing| existing == included) {
+                    out.source.push_str(&format!("#line 1 {}\n", out.files.len()));
+                    self.expand(included, &[], out).map_err(|e| format!("{}:{}: {}", name, line_number, e))?;
+                }
+                out.source.push_str(&format!("#line {} {}\n", line_number + 1, file));
+            } else {
+                out.source.push_str(line);
+                out.source.push('\n');
+            }
+        }
+        Ok(())
+    }
+
+    // preprocesses and compiles a shader of `kind`, mapping compile errors back to the original files
+    fn compile_shader(&self, name: &str, kind: gl::types::GLenum, defines: &[&str], files: &mut Vec<String>) -> Result<Shader, String> {
+        let preprocessed = self.preprocess(name, defines)?;
+        let source = std::ffi::CString::new(preprocessed.source.as_str()).map_err(|e| e.to_string())?;
+        let shader = Shader::from_source(&source, kind).map_err(|log| format!("Couldn't compile {}:\n{}", name, preprocessed.map_log(&log)))?;
+        files.extend(preprocessed.files);
+        Ok(shader)
+    }
+
+    // compiles and links a program, returning it with the names of every file it was built from
+    pub fn compile(&self, vert: &str, frag: &str, defines: &[&str]) -> Result<(Program, Vec<String>), String> {
+        let mut files = Vec::new();
+        let vert_shader = self.compile_shader(vert, gl::VERTEX_SHADER, defines, &mut files)?;
+        let frag_shader = self.compile_shader(frag, gl::FRAGMENT_SHADER, defines, &mut files)?;
+        let program = Program::from_shaders(&[vert_shader, frag_shader]).map_err(|log| format!("Couldn't link {} + {}:\n{}", vert, frag, log))?;
+
+        files.sort();
+        files.dedup();
+        Ok((program, files))
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use rand::RngCore;
+
+// the streams the engine's games share, others are created by name as they're used
+pub const GAMEPLAY: &str = "gameplay"; // anything that changes the game's state, replays depend on it
+#[allow(dead_code)]
+pub const COSMETIC: &str = "cosmetic"; // particles, screen shake and such, free to vary between runs
+
+// the engine's random numbers, seeded so a run can be reproduced from its seed
+// games take it from the world as a resource instead of calling rand::rng()
+// each named stream has its own sequence, so drawing more cosmetic numbers doesn't change gameplay ones
+pub struct Random {
+    seed: u64,
+    streams: BTreeMap<String, Stream>,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { seed, streams: BTreeMap::new() }
+    }
+
+    // a different seed every run
+    #[allow(dead_code)]
+    pub fn from_entropy() -> Self {
+        Random::new(rand::random())
+    }
+
+    #[allow(dead_code)]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // starts every stream over from a new seed, like for a game that reads its seed from its own config
+    #[allow(dead_code)]
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    // the stream with this name, started from the seed the first time it's used
+    // names are words without whitespace, so snapshots can save them as they are, release builds only catch bad names on save
+    pub fn stream(&mut self, name: &str) -> &mut Stream {
+        debug_assert!(is_stream_name(name), "random stream name \"{}\" is empty or has whitespace", name);
+        let seed = self.seed;
+        self.streams.entry(name.to_string()).or_insert_with(|| Stream::new(seed, name))
+    }
+
+    pub fn gameplay(&mut self) -> &mut Stream {
+        self.stream(GAMEPLAY)
+    }
+
+    #[allow(dead_code)]
+    pub fn cosmetic(&mut self) -> &mut Stream {
+        self.stream(COSMETIC)
+    }
+
+    // the seed and where every stream is in its sequence, for save games and replays that start mid-run
+    #[allow(dead_code)]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            seed: self.seed,
+            streams: self.streams.iter().map(|(name, stream)| (name.clone(), stream.state)).collect(),
+        }
+    }
+
+    // continues from a snapshot, streams it doesn't have hadn't been used yet so they start from the seed
+    #[allow(dead_code)]
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.seed = snapshot.seed;
+        self.streams = snapshot.streams.iter().map(|(name, state)| (name.clone(), Stream { state: *state })).collect();
+    }
+}
+
+// one sequence of random numbers, rand's Rng methods like random_range work on it
+// splitmix64, whose whole state is one number so snapshots stay small
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stream {
+    state: u64,
+}
+
+impl Stream {
+    fn new(seed: u64, name: &str) -> Self {
+        // fnv-1a, which unlike std's hashers is guaranteed to give the same value in every build
+        let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+        Stream { state: mix(seed ^ mix(hash)) }
+    }
+}
+
+impl RngCore for Stream {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        mix(self.state)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand::rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+}
+
+fn is_stream_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(char::is_whitespace)
+}
+
+fn mix(value: u64) -> u64 {
+    let value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+// the state of a Random at one point, restored with Random::restore
+pub struct Snapshot {
+    pub seed: u64,
+    pub streams: Vec<(String, u64)>, // each stream's name and state, in name order
+}
+
+impl Snapshot {
+    #[allow(dead_code)]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        Snapshot::parse(&source, &path.display().to_string())
+    }
+
+    // a seed line, then one line per stream:
+    //   seed <number>
+    //   stream <name> <state>
+    pub fn parse(source: &str, file: &str) -> Result<Self, String> {
+        let mut seed = None;
+        let mut streams = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let error = |message: String| format!("{}:{}: {}", file, number + 1, message);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [] => {},
+                ["seed", value] => seed = Some(value.parse().map_err(|e| error(format!("bad seed \"{}\": {}", value, e)))?),
+                ["stream", name, state] => {
+                    let state = state.parse().map_err(|e| error(format!("bad state \"{}\": {}", state, e)))?;
+                    streams.push((name.to_string(), state));
+                },
+                _ => return Err(error(format!("expected \"seed <number>\" or \"stream <name> <state>\", found \"{}\"", line))),
+            }
+        }
+        let seed = seed.ok_or_else(|| format!("{}: missing seed", file))?;
+        Ok(Snapshot { seed, streams })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("seed {}\n", self.seed);
+        for (name, state) in &self.streams {
+            text += &format!("stream {} {}\n", name, state);
+        }
+        text
+    }
+
+    #[allow(dead_code)]
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        // a snapshot built by hand could have names that wouldn't read back
+        if let Some((name, _)) = self.streams.iter().find(|(name, _)| !is_stream_name(name)) {
+            return Err(format!("Couldn't write {}: stream name \"{}\" is empty or has whitespace", path.display(), name));
+        }
+        fs::write(path, self.to_text()).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn draw(stream: &mut Stream, count: usize) -> Vec<u64> {
+        (0..count).map(|_| stream.next_u64()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_sequences() {
+        let (mut a, mut b) = (Random::new(42), Random::new(42));
+        assert_eq!(draw(a.gameplay(), 16), draw(b.gameplay(), 16));
+        assert_eq!(draw(a.stream("enemies"), 16), draw(b.stream("enemies"), 16));
+        assert_ne!(draw(Random::new(43).gameplay(), 16), draw(Random::new(42).gameplay(), 16));
+        assert_ne!(draw(a.stream("enemies"), 16), draw(a.stream("loot"), 16));
+    }
+
+    #[test]
+    fn streams_dont_affect_each_other() {
+        let mut quiet = Random::new(7);
+        let mut noisy = Random::new(7);
+        let expected = draw(quiet.gameplay(), 8);
+
+        // drawing cosmetic numbers in between, and in a different order, leaves gameplay as it was
+        let mut drawn = draw(noisy.cosmetic(), 100);
+        drawn.extend(draw(noisy.gameplay(), 4));
+        for _ in 0..50 {
+            noisy.cosmetic().random_range(0.0..1.0f32);
+        }
+        drawn.extend(draw(noisy.gameplay(), 4));
+        assert_eq!(drawn[100..], expected[..]);
+    }
+
+    #[test]
+    fn restored_snapshots_continue_the_same_sequences() {
+        let mut random = Random::new(1234);
+        draw(random.gameplay(), 5);
+        draw(random.cosmetic(), 3);
+        let snapshot = random.snapshot();
+        let expected = (draw(random.gameplay(), 10), draw(random.cosmetic(), 10), draw(random.stream("later"), 10));
+
+        let text = snapshot.to_text();
+        let parsed = Snapshot::parse(&text, "snapshot").unwrap();
+        assert_eq!(parsed, snapshot);
+
+        let mut restored = Random::new(0);
+        draw(restored.gameplay(), 3);
+        restored.restore(&parsed);
+        assert_eq!(restored.seed(), 1234);
+        // streams unused at the snapshot start from the seed, like they would have
+        let continued = (draw(restored.gameplay(), 10), draw(restored.cosmetic(), 10), draw(restored.stream("later"), 10));
+        assert_eq!(continued, expected);
+    }
+
+    #[test]
+    fn malformed_snapshots_are_rejected() {
+        for (source, expected) in [
+            ("stream gameplay 5\n", "s: missing seed"),
+            ("seed x\n", "s:1: bad seed \"x\": invalid digit found in string"),
+            ("seed 1\nstream gameplay\n", "s:2: expected \"seed <number>\" or \"stream <name> <state>\", found \"stream gameplay\""),
+            ("seed 1\nstream enemy spawns 5\n", "s:2: expected \"seed <number>\" or \"stream <name> <state>\", found \"stream enemy spawns 5\""),
+            ("seed 1\nstream gameplay -1\n", "s:2: bad state \"-1\": invalid digit found in string"),
+        ] {
+            assert_eq!(Snapshot::parse(source, "s").err().as_deref(), Some(expected), "parsing {:?}", source);
+        }
+    }
+
+    #[test]
+    fn snapshots_with_names_that_wouldnt_read_back_arent_saved() {
+        let snapshot = Snapshot { seed: 1, streams: vec![("enemy spawns".to_string(), 5)] };
+        let error = snapshot.save(Path::new("unwritten.snapshot")).unwrap_err();
+        assert!(error.contains("\"enemy spawns\" is empty or has whitespace"), "{}", error);
+        assert!(!Path::new("unwritten.snapshot").exists());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "has whitespace")]
+    fn stream_names_with_whitespace_panic() {
+        Random::new(1).stream("enemy spawns");
+    }
+}
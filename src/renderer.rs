@@ -0,0 +1,227 @@
+use glam::{Mat4, Vec4};
+
+use crate::animation::AnimationPlayer;
+use crate::assets::{Assets, ProgramHandle};
+use crate::components::{Color, Shadows};
+use crate::culling::{Aabb, Frustum};
+use crate::ecs::World;
+use crate::graphics::*;
+use crate::scene::GlobalTransform;
+use crate::material::{self, BlendMode, MaterialHandle};
+use crate::mesh::MeshHandle;
+use crate::shadow::ShadowRenderer;
+
+// the render components of an entity, gathered for a frame by extract
+pub struct Drawable<'w> {
+    pub mesh: &'w MeshHandle,
+    pub material: MaterialHandle,
+    pub color: Vec4,
+    pub model_matrix: Mat4,
+    pub bounds: Aabb, // in world space
+    pub shadows: Shadows,
+    pub animation: Option<&'w AnimationPlayer>, // for skinned meshes, drawn with the player's joint matrices
+}
+
+// every entity with a mesh, a material and a global transform
+pub fn extract(world: &World) -> Vec<Drawable<'_>> {
+    world.query3::<MeshHandle, MaterialHandle, GlobalTransform>()
+        .map(|(entity, mesh, material, global)| Drawable {
+            mesh,
+            material: *material,
+            color: world.get::<Color>(entity).map_or(Vec4::ONE, |color| color.0),
+            model_matrix: global.0,
+            bounds: mesh.bounds().transformed(&global.0),
+            shadows: world.get::<Shadows>(entity).copied().unwrap_or_default(),
+            animation: world.get::<AnimationPlayer>(entity),
+        })
+        .collect()
+}
+
+// an object waiting to be drawn, with the state it needs resolved for sorting
+struct DrawItem<'a> {
+    object: &'a Drawable<'a>,
+    program: ProgramHandle,
+    material: MaterialHandle,
+    mesh: usize,
+    depth: f32, // view space z, more negative is farther from the camera
+}
+
+impl DrawItem<'_> {
+    // items that can be drawn by the same instanced draw call
+    // skinned objects each have their own joint matrices, so they're drawn one at a time
+    fn batches_with(&self, other: &DrawItem) -> bool {
+        self.object.animation.is_none()
+            && other.object.animation.is_none()
+            && self.material == other.material
+            && self.mesh == other.mesh
+            && self.object.shadows.receive == other.object.shadows.receive
+    }
+}
+
+// collects the objects to draw in a frame and orders them
+// opaque items are sorted by program, material, then mesh to minimize state changes
+// transparent items are sorted back-to-front so blending composites them correctly
+pub struct RenderQueue<'a> {
+    opaque: Vec<DrawItem<'a>>,
+    transparent: Vec<DrawItem<'a>>,
+}
+
+impl<'a> RenderQueue<'a> {
+    pub fn new() -> Self {
+        RenderQueue {
+            opaque: Vec::new(),
+            transparent: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, object: &'a Drawable<'a>, assets: &Assets, view_matrix: &Mat4) {
+        let material = assets.material(object.material);
+        let item = DrawItem {
+            object,
+            program: material.program,
+            material: object.material,
+            mesh: object.mesh.id(),
+            depth: (*view_matrix * object.model_matrix.w_axis).z,
+        };
+
+        if material.blend_mode != BlendMode::Opaque || object.color.w < 1. {
+            self.transparent.push(item);
+        } else {
+            self.opaque.push(item);
+        }
+    }
+
+    pub fn sort(&mut self) {
+        self.opaque.sort_by_key(|item| (item.program, item.material, item.mesh, item.object.shadows.receive));
+        self.transparent.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+    }
+}
+
+// splits sorted items into runs that can be drawn instanced, keeping their order
+fn batches<'q, 'a>(items: &'q [DrawItem<'a>]) -> impl Iterator<Item = &'q [DrawItem<'a>]> {
+    items.chunk_by(|a, b| a.batches_with(b))
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+// counts from the last scene pass, shadow passes aren't included
+pub struct FrameStats {
+    pub drawn: usize,
+    pub culled: usize, // outside the camera frustum
+    pub draw_calls: usize,
+}
+
+// draws objects with glDrawElementsInstanced, one call per batch of items sharing a mesh and material
+pub struct Renderer {
+    instances: Vbo,
+    instance_data: Vec<InstanceData>,
+    stats: FrameStats,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer {
+            instances: Vbo::gen(),
+            instance_data: Vec::new(),
+            stats: FrameStats::default(),
+        }
+    }
+
+    // uploads the transforms and colors of `objects` and draws them, they must all share a mesh
+    fn draw_instanced<'a, 'w: 'a>(&mut self, objects: impl Iterator<Item = &'a Drawable<'w>>) {
+        self.instance_data.clear();
+        let mut mesh = None;
+        for object in objects {
+            mesh.get_or_insert_with(|| object.mesh.clone());
+            self.instance_data.push(InstanceData {
+                model_matrix: object.model_matrix,
+                color: object.color,
+            });
+        }
+        let Some(mesh) = mesh else { return };
+        self.instances.set(&self.instance_data);
+
+        mesh.vao().setup_instances(&self.instances);
+        unsafe {
+            gl::DrawElementsInstanced(gl::TRIANGLES, mesh.index_count(), gl::UNSIGNED_INT, std::ptr::null(), self.instance_data.len() as i32);
+        }
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    // draws the objects whose world bounds intersect `frustum`
+    pub fn render(&mut self, objects: &[Drawable], assets: &Assets, shadows: &ShadowRenderer, frustum: &Frustum, view_matrix: &Mat4) {
+        self.stats = FrameStats::default();
+
+        let mut queue = RenderQueue::new();
+        for object in objects {
+            if !frustum.intersects_aabb(&object.bounds) {
+                self.stats.culled += 1;
+                continue;
+            }
+            queue.push(object, assets, view_matrix);
+        }
+        queue.sort();
+        self.stats.drawn = queue.opaque.len() + queue.transparent.len();
+
+        let mut current_program = None;
+        for (items, transparent) in [(&queue.opaque, false), (&queue.transparent, true)] {
+            for batch in batches(items) {
+                let material = assets.material(batch[0].material);
+                let program = assets.program(material.program);
+
+                // shadow uniforms only need to be set once per program
+                if current_program != Some(material.program) {
+                    program.set();
+                    shadows.bind(program);
+                    current_program = Some(material.program);
+                }
+
+                material.apply(program);
+                if transparent {
+                    // objects can be translucent through their color even if their material is opaque
+                    // and shouldn't hide what's drawn behind them later
+                    unsafe {
+                        if material.blend_mode == BlendMode::Opaque {
+                            gl::Enable(gl::BLEND);
+                            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                        }
+                        gl::DepthMask(gl::FALSE);
+                    }
+                }
+                if program.has_uniform("u_receive_shadows") {
+                    program.set_bool("u_receive_shadows", batch[0].object.shadows.receive);
+                }
+                if let Some(animation) = batch[0].object.animation {
+                    if program.has_uniform("u_joint_matrices") {
+                        program.set_mat4_array("u_joint_matrices", animation.joint_matrices());
+                    }
+                }
+                self.draw_instanced(batch.iter().map(|item| item.object));
+                self.stats.draw_calls += 1;
+            }
+        }
+        material::reset_render_state();
+    }
+
+    // draws only the geometry of `objects` with the program in use, for depth passes
+    // skinned objects are skipped, see render_depth_skinned
+    pub fn render_depth<'a, 'w: 'a>(&mut self, objects: impl Iterator<Item = &'a Drawable<'w>>) {
+        let mut objects: Vec<&Drawable> = objects.filter(|object| object.animation.is_none()).collect();
+        objects.sort_by_key(|object| object.mesh.id());
+        for batch in objects.chunk_by(|a, b| a.mesh == b.mesh) {
+            self.draw_instanced(batch.iter().copied());
+        }
+    }
+
+    // draws the geometry of the skinned objects among `objects` with `program`, which must be in use
+    // and compiled with SKINNING
+    pub fn render_depth_skinned<'a, 'w: 'a>(&mut self, objects: impl Iterator<Item = &'a Drawable<'w>>, program: &Program) {
+        for object in objects {
+            let Some(animation) = object.animation else { continue };
+            program.set_mat4_array("u_joint_matrices", animation.joint_matrices());
+            self.draw_instanced(std::iter::once(object));
+        }
+    }
+}
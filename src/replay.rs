@@ -0,0 +1,368 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
+
+use sdl2::controller::{Axis, Button as PadButton};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::mouse::{MouseButton, MouseState, MouseWheelDirection};
+use sdl2::EventPump;
+
+const HEADER: &str = "input-recording 1";
+
+#[derive(Clone, Debug, PartialEq)]
+// one frame's events and the time the game steps by for it
+pub struct Frame {
+    pub delta_time: f32,
+    pub events: Vec<Event>,
+}
+
+// where the main loop gets its events and delta time from every frame
+pub trait InputSource {
+    fn next_frame(&mut self, pump: &mut EventPump) -> Frame;
+}
+
+// events straight from SDL, stepped by the time since the last frame or by a fixed delta
+pub struct LiveInput {
+    last_frame_time: Instant,
+    fixed_delta: Option<f32>,
+}
+
+impl LiveInput {
+    pub fn new(fixed_delta: Option<f32>) -> Self {
+        LiveInput { last_frame_time: Instant::now(), fixed_delta }
+    }
+}
+
+impl InputSource for LiveInput {
+    fn next_frame(&mut self, pump: &mut EventPump) -> Frame {
+        let elapsed = self.last_frame_time.elapsed().as_secs_f32();
+        self.last_frame_time = Instant::now();
+        Frame {
+            delta_time: self.fixed_delta.unwrap_or(elapsed),
+            events: pump.poll_iter().collect(),
+        }
+    }
+}
+
+// passes another source's frames through, writing their input events and delta times to a file
+// the file is flushed every frame, so a recording of a crash keeps everything up to it
+pub struct Recorder {
+    source: Box<dyn InputSource>,
+    writer: Option<BufWriter<File>>, // None after a write fails
+}
+
+impl Recorder {
+    // the seed is saved so the replay can seed the game's random numbers the same way
+    pub fn create(source: Box<dyn InputSource>, path: &Path, seed: u64) -> Result<Self, String> {
+        let error = |e: std::io::Error| format!("Couldn't write {}: {}", path.display(), e);
+        let mut writer = BufWriter::new(File::create(path).map_err(error)?);
+        Recorder::write_header(&mut writer, seed).map_err(error)?;
+        Ok(Recorder { source, writer: Some(writer) })
+    }
+
+    fn write_header(writer: &mut impl Write, seed: u64) -> std::io::Result<()> {
+        writeln!(writer, "{}\nseed {}", HEADER, seed)
+    }
+
+    fn write(writer: &mut impl Write, frame: &Frame) -> std::io::Result<()> {
+        // floats print with enough digits to read back exactly
+        writeln!(writer, "frame {}", frame.delta_time)?;
+        for line in frame.events.iter().filter_map(encode) {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()
+    }
+}
+
+impl InputSource for Recorder {
+    fn next_frame(&mut self, pump: &mut EventPump) -> Frame {
+        let frame = self.source.next_frame(pump);
+        if let Some(writer) = &mut self.writer {
+            if let Err(e) = Recorder::write(writer, &frame) {
+                println!("Couldn't write recording, stopping it: {}", e);
+                self.writer = None;
+            }
+        }
+        frame
+    }
+}
+
+// plays a recording back frame by frame with its recorded delta times, then carries on with live input
+// input from SDL is ignored while it plays, other events like quitting and resizing still come through
+pub struct Replay {
+    seed: u64,
+    frames: std::vec::IntoIter<Frame>,
+    fixed_delta: Option<f32>, // for the live input after the recording ends
+    live: Option<LiveInput>,
+}
+
+impl Replay {
+    pub fn load(path: &Path, fixed_delta: Option<f32>) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        Replay::parse(&source, &path.display().to_string(), fixed_delta)
+    }
+
+    pub fn parse(source: &str, file: &str, fixed_delta: Option<f32>) -> Result<Self, String> {
+        let mut lines = source.lines().enumerate();
+        if lines.next().map(|(_, line)| line.trim()) != Some(HEADER) {
+            return Err(format!("{}: not an input recording", file));
+        }
+        let seed = match lines.next().map(|(_, line)| line.split_once(' ')) {
+            Some(Some(("seed", seed))) => seed.trim().parse().map_err(|e| format!("{}:2: bad seed: {}", file, e))?,
+            _ => return Err(format!("{}:2: expected \"seed <number>\"", file)),
+        };
+
+        let mut frames: Vec<Frame> = Vec::new();
+        for (number, line) in lines {
+            let error = |message: String| format!("{}:{}: {}", file, number + 1, message);
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(delta_time) = line.strip_prefix("frame ") {
+                let delta_time = delta_time.trim().parse().map_err(|e| error(format!("bad delta time: {}", e)))?;
+                frames.push(Frame { delta_time, events: Vec::new() });
+                continue;
+            }
+            let frame = frames.last_mut().ok_or_else(|| error("event before the first frame".to_string()))?;
+            frame.events.push(decode(line).map_err(error)?);
+        }
+        Ok(Replay { seed, frames: frames.into_iter(), fixed_delta, live: None })
+    }
+
+    // the seed the recorded run used
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn frames_left(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl InputSource for Replay {
+    fn next_frame(&mut self, pump: &mut EventPump) -> Frame {
+        if self.live.is_none() && self.frames.len() == 0 {
+            println!("Replay finished, switching to live input");
+            self.live = Some(LiveInput::new(self.fixed_delta));
+        }
+        if let Some(live) = &mut self.live {
+            return live.next_frame(pump);
+        }
+        let mut events: Vec<Event> = pump.poll_iter().filter(|event| !is_input_event(event)).collect();
+        let frame = self.frames.next().unwrap();
+        events.extend(frame.events);
+        Frame { delta_time: frame.delta_time, events }
+    }
+}
+
+// whether the event is one that recordings keep and replays supply
+pub fn is_input_event(event: &Event) -> bool {
+    encode(event).is_some()
+}
+
+// an input event as one line of a recording, None for events that aren't input
+fn encode(event: &Event) -> Option<String> {
+    let key = |keycode: &Option<Keycode>, scancode: &Option<Scancode>, keymod: &Mod, repeat: &bool| {
+        let keycode = keycode.map_or("none".to_string(), |keycode| keycode.into_i32().to_string());
+        let scancode = scancode.map_or("none".to_string(), |scancode| (scancode as i32).to_string());
+        format!("{} {} {} {}", keycode, scancode, keymod.bits(), repeat)
+    };
+    let line = match event {
+        Event::KeyDown { keycode, scancode, keymod, repeat, .. } => format!("key down {}", key(keycode, scancode, keymod, repeat)),
+        Event::KeyUp { keycode, scancode, keymod, repeat, .. } => format!("key up {}", key(keycode, scancode, keymod, repeat)),
+        Event::MouseMotion { mousestate, x, y, xrel, yrel, .. } => {
+            format!("mouse motion {} {} {} {} {}", mousestate.to_sdl_state(), x, y, xrel, yrel)
+        },
+        Event::MouseButtonDown { mouse_btn, clicks, x, y, .. } => format!("mouse down {} {} {} {}", *mouse_btn as u8, clicks, x, y),
+        Event::MouseButtonUp { mouse_btn, clicks, x, y, .. } => format!("mouse up {} {} {} {}", *mouse_btn as u8, clicks, x, y),
+        Event::MouseWheel { x, y, direction, precise_x, precise_y, mouse_x, mouse_y, .. } => {
+            format!("wheel {} {} {} {} {} {} {}", x, y, direction.to_ll(), precise_x, precise_y, mouse_x, mouse_y)
+        },
+        Event::ControllerButtonDown { which, button, .. } => format!("pad down {} {}", which, button.string()),
+        Event::ControllerButtonUp { which, button, .. } => format!("pad up {} {}", which, button.string()),
+        Event::ControllerAxisMotion { which, axis, value, .. } => format!("pad axis {} {} {}", which, axis.string(), value),
+        Event::ControllerDeviceAdded { which, .. } => format!("pad added {}", which),
+        Event::ControllerDeviceRemoved { which, .. } => format!("pad removed {}", which),
+        Event::Window { win_event: WindowEvent::FocusGained, .. } => "focus gained".to_string(),
+        Event::Window { win_event: WindowEvent::FocusLost, .. } => "focus lost".to_string(),
+        _ => return None,
+    };
+    Some(line)
+}
+
+// the event from a line written by encode, timestamps and window ids aren't kept so they read back as 0
+fn decode(line: &str) -> Result<Event, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let field = |index: usize| fields.get(index).copied().ok_or_else(|| format!("missing field {} in \"{}\"", index + 1, line));
+    let number = |index: usize| -> Result<i32, String> { parse(field(index)?) };
+    let key_fields = || -> Result<(Option<Keycode>, Option<Scancode>, Mod, bool), String> {
+        let keycode = match field(2)? {
+            "none" => None,
+            keycode => Some(Keycode::from_i32(parse(keycode)?).ok_or_else(|| format!("unknown keycode {}", keycode))?),
+        };
+        let scancode = match field(3)? {
+            "none" => None,
+            scancode => Some(Scancode::from_i32(parse(scancode)?).ok_or_else(|| format!("unknown scancode {}", scancode))?),
+        };
+        Ok((keycode, scancode, Mod::from_bits_truncate(parse(field(4)?)?), parse(field(5)?)?))
+    };
+    let pad_button = |index: usize| -> Result<PadButton, String> {
+        let name = field(index)?;
+        PadButton::from_string(name).ok_or_else(|| format!("unknown pad button \"{}\"", name))
+    };
+
+    let event = match (field(0)?, fields.get(1).copied().unwrap_or("")) {
+        ("key", "down") => {
+            let (keycode, scancode, keymod, repeat) = key_fields()?;
+            Event::KeyDown { timestamp: 0, window_id: 0, keycode, scancode, keymod, repeat }
+        },
+        ("key", "up") => {
+            let (keycode, scancode, keymod, repeat) = key_fields()?;
+            Event::KeyUp { timestamp: 0, window_id: 0, keycode, scancode, keymod, repeat }
+        },
+        ("mouse", "motion") => Event::MouseMotion {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mousestate: MouseState::from_sdl_state(parse(field(2)?)?),
+            x: number(3)?,
+            y: number(4)?,
+            xrel: number(5)?,
+            yrel: number(6)?,
+        },
+        ("mouse", "down") => Event::MouseButtonDown {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mouse_btn: MouseButton::from_ll(parse(field(2)?)?),
+            clicks: parse(field(3)?)?,
+            x: number(4)?,
+            y: number(5)?,
+        },
+        ("mouse", "up") => Event::MouseButtonUp {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mouse_btn: MouseButton::from_ll(parse(field(2)?)?),
+            clicks: parse(field(3)?)?,
+            x: number(4)?,
+            y: number(5)?,
+        },
+        ("wheel", _) => Event::MouseWheel {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            x: number(1)?,
+            y: number(2)?,
+            direction: MouseWheelDirection::from_ll(parse(field(3)?)?),
+            precise_x: parse(field(4)?)?,
+            precise_y: parse(field(5)?)?,
+            mouse_x: number(6)?,
+            mouse_y: number(7)?,
+        },
+        ("pad", "down") => Event::ControllerButtonDown { timestamp: 0, which: parse(field(2)?)?, button: pad_button(3)? },
+        ("pad", "up") => Event::ControllerButtonUp { timestamp: 0, which: parse(field(2)?)?, button: pad_button(3)? },
+        ("pad", "axis") => {
+            let name = field(3)?;
+            let axis = Axis::from_string(name).ok_or_else(|| format!("unknown pad axis \"{}\"", name))?;
+            Event::ControllerAxisMotion { timestamp: 0, which: parse(field(2)?)?, axis, value: parse(field(4)?)? }
+        },
+        ("pad", "added") => Event::ControllerDeviceAdded { timestamp: 0, which: parse(field(2)?)? },
+        ("pad", "removed") => Event::ControllerDeviceRemoved { timestamp: 0, which: parse(field(2)?)? },
+        ("focus", "gained") => Event::Window { timestamp: 0, window_id: 0, win_event: WindowEvent::FocusGained },
+        ("focus", "lost") => Event::Window { timestamp: 0, window_id: 0, win_event: WindowEvent::FocusLost },
+        _ => return Err(format!("unknown event \"{}\"", line)),
+    };
+    Ok(event)
+}
+
+fn parse<T: FromStr>(text: &str) -> Result<T, String> where T::Err: std::fmt::Display {
+    text.parse().map_err(|e| format!("bad value \"{}\": {}", text, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one of every event kind a recording keeps, with values that don't default to 0
+    fn input_events() -> Vec<Event> {
+        vec![
+            Event::KeyDown { timestamp: 0, window_id: 0, keycode: Some(Keycode::A), scancode: Some(Scancode::A), keymod: Mod::LSHIFTMOD | Mod::NUMMOD, repeat: true },
+            Event::KeyUp { timestamp: 0, window_id: 0, keycode: None, scancode: None, keymod: Mod::NOMOD, repeat: false },
+            Event::MouseMotion { timestamp: 0, window_id: 0, which: 0, mousestate: MouseState::from_sdl_state(5), x: 10, y: -20, xrel: 3, yrel: -4 },
+            Event::MouseButtonDown { timestamp: 0, window_id: 0, which: 0, mouse_btn: MouseButton::Right, clicks: 2, x: 7, y: 8 },
+            Event::MouseButtonUp { timestamp: 0, window_id: 0, which: 0, mouse_btn: MouseButton::X2, clicks: 1, x: 9, y: 10 },
+            Event::MouseWheel {
+                timestamp: 0,
+                window_id: 0,
+                which: 0,
+                x: -1,
+                y: 2,
+                direction: MouseWheelDirection::Flipped,
+                precise_x: -0.75,
+                precise_y: 2.125,
+                mouse_x: 30,
+                mouse_y: 40,
+            },
+            Event::ControllerButtonDown { timestamp: 0, which: 1, button: PadButton::A },
+            Event::ControllerButtonUp { timestamp: 0, which: 1, button: PadButton::DPadLeft },
+            Event::ControllerAxisMotion { timestamp: 0, which: 2, axis: Axis::TriggerRight, value: -32768 },
+            Event::ControllerDeviceAdded { timestamp: 0, which: 3 },
+            Event::ControllerDeviceRemoved { timestamp: 0, which: 3 },
+            Event::Window { timestamp: 0, window_id: 0, win_event: WindowEvent::FocusGained },
+            Event::Window { timestamp: 0, window_id: 0, win_event: WindowEvent::FocusLost },
+        ]
+    }
+
+    fn record(seed: u64, frames: &[Frame]) -> String {
+        let mut text = Vec::new();
+        Recorder::write_header(&mut text, seed).unwrap();
+        for frame in frames {
+            Recorder::write(&mut text, frame).unwrap();
+        }
+        String::from_utf8(text).unwrap()
+    }
+
+    #[test]
+    fn every_input_event_round_trips() {
+        for event in input_events() {
+            let line = encode(&event).unwrap_or_else(|| panic!("{:?} isn't recorded", event));
+            assert_eq!(decode(&line), Ok(event), "{}", line);
+        }
+    }
+
+    #[test]
+    fn recordings_read_back_frames_and_seed_exactly() {
+        let frames = vec![
+            Frame { delta_time: 1. / 60., events: input_events() },
+            Frame { delta_time: 0., events: Vec::new() },
+            Frame { delta_time: 0.1 + 0.2, events: input_events()[..2].to_vec() },
+            Frame { delta_time: f32::MIN_POSITIVE, events: Vec::new() },
+        ];
+        let replay = Replay::parse(&record(u64::MAX, &frames), "test", None).unwrap();
+        assert_eq!(replay.seed(), u64::MAX);
+        assert_eq!(replay.frames.as_slice(), frames.as_slice());
+    }
+
+    #[test]
+    fn events_that_arent_input_are_not_recorded() {
+        let quit = Event::Quit { timestamp: 0 };
+        let resized = Event::Window { timestamp: 0, window_id: 0, win_event: WindowEvent::Resized(800, 600) };
+        let frame = Frame { delta_time: 0.5, events: vec![quit, resized] };
+        let replay = Replay::parse(&record(1, &[frame]), "test", None).unwrap();
+        assert_eq!(replay.frames.as_slice(), &[Frame { delta_time: 0.5, events: Vec::new() }]);
+    }
+
+    #[test]
+    fn malformed_recordings_are_rejected() {
+        assert!(Replay::parse("seed 1\n", "test", None).is_err());
+        assert!(Replay::parse("input-recording 1\nseed x\n", "test", None).is_err());
+        assert!(Replay::parse("input-recording 1\nseed 1\nkey down 97 4 0 false\n", "test", None).is_err());
+        assert!(Replay::parse("input-recording 1\nseed 1\nframe 0.1\nkey down 97\n", "test", None).is_err());
+        assert!(Replay::parse("input-recording 1\nseed 1\nframe 0.1\npad down 0 nonsense\n", "test", None).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use glam::Mat4;
+
+use crate::ecs::{Entity, World};
+use crate::transform::Transform;
+
+// entities attached to parents whose world transform they follow
+// an entity's Transform is relative to its parent's, and update_transforms propagates changes to GlobalTransforms
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+// the entity this one is attached to, its Transform is relative to the parent's
+pub struct Parent(pub Entity);
+
+#[derive(Clone, Debug, Default)]
+pub struct Children(pub Vec<Entity>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// the model matrix of an entity with a Transform, kept up to date by update_transforms
+pub struct GlobalTransform(pub Mat4);
+
+impl World {
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.get::<Parent>(entity).map(|parent| parent.0)
+    }
+
+    #[allow(dead_code)]
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        self.get::<Children>(entity).map_or(&[], |children| &children.0)
+    }
+
+    // whether `ancestor` is `entity` or one of its ancestors
+    fn is_ancestor(&self, ancestor: Entity, entity: Entity) -> bool {
+        let mut current = Some(entity);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.parent(node);
+        }
+        false
+    }
+
+    // the entity's model matrix computed through its ancestors, current even before the transforms are propagated
+    pub fn world_matrix(&self, entity: Entity) -> Mat4 {
+        let mut matrix = Mat4::IDENTITY;
+        let mut current = Some(entity);
+        while let Some(node) = current {
+            if let Some(transform) = self.get::<Transform>(node) {
+                matrix = transform.matrix() * matrix;
+            }
+            current = self.parent(node);
+        }
+        matrix
+    }
+
+    // spawns an entity with `transform` relative to `parent`
+    pub fn spawn_child(&mut self, parent: Entity, transform: Transform) -> Entity {
+        let child = self.spawn();
+        self.insert(child, transform);
+        self.insert(child, Parent(parent));
+        match self.get_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => self.insert(parent, Children(vec![child])),
+        }
+        child
+    }
+
+    // attaches `child` to `parent`, or detaches it with None, without moving it in the world
+    #[allow(dead_code)]
+    pub fn set_parent(&mut self, child: Entity, parent: Option<Entity>) -> Result<(), String> {
+        if !self.is_alive(child) {
+            return Err("Couldn't reparent a despawned entity".to_string());
+        }
+        if let Some(parent) = parent {
+            if !self.is_alive(parent) {
+                return Err("Couldn't reparent to a despawned entity".to_string());
+            }
+            if self.is_ancestor(child, parent) {
+                return Err("Couldn't reparent an entity to itself or one of its descendants".to_string());
+            }
+        }
+
+        let world = self.world_matrix(child);
+        let parent_world = parent.map_or(Mat4::IDENTITY, |parent| self.world_matrix(parent));
+
+        if let Some(Parent(old)) = self.remove::<Parent>(child) {
+            if let Some(children) = self.get_mut::<Children>(old) {
+                children.0.retain(|other| *other != child);
+            }
+        }
+        if let Some(parent) = parent {
+            self.insert(child, Parent(parent));
+            match self.get_mut::<Children>(parent) {
+                Some(children) => children.0.push(child),
+                None => self.insert(parent, Children(vec![child])),
+            }
+        }
+
+        // marks the transform changed, so the new parent's transform is propagated to it
+        if let Some(transform) = self.get_mut::<Transform>(child) {
+            *transform = Transform::from_matrix(&(parent_world.inverse() * world));
+        }
+        Ok(())
+    }
+
+    // despawns the entity and all of its descendants, returning whether it was alive
+    #[allow(dead_code)]
+    pub fn despawn_recursive(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        if let Some(Parent(parent)) = self.get::<Parent>(entity).copied() {
+            if let Some(children) = self.get_mut::<Children>(parent) {
+                children.0.retain(|other| *other != entity);
+            }
+        }
+
+        let mut stack = vec![entity];
+        while let Some(node) = stack.pop() {
+            if let Some(children) = self.remove::<Children>(node) {
+                stack.extend(children.0);
+            }
+            self.despawn(node);
+        }
+        true
+    }
+}
+
+// recomputes the GlobalTransform of every entity whose Transform changed since the last run, and of their descendants
+// descendants are only reached through entities with a Transform
+pub fn update_transforms(world: &mut World) {
+    let Some(transforms) = world.storage::<Transform>() else { return };
+
+    // entities whose parent was despawned without them are treated as roots
+    let mut stack: Vec<(Entity, Mat4, bool)> = transforms.iter()
+        .filter(|(entity, _)| !world.get::<Parent>(*entity).is_some_and(|parent| world.is_alive(parent.0)))
+        .map(|(entity, _)| (entity, Mat4::IDENTITY, false))
+        .collect();
+
+    let mut updates = Vec::new();
+    while let Some((entity, parent_world, parent_changed)) = stack.pop() {
+        let Some(transform) = transforms.get(entity) else { continue };
+        let previous = world.get::<GlobalTransform>(entity);
+        let changed = parent_changed || transforms.is_changed(entity) || previous.is_none();
+        let global = match previous {
+            Some(previous) if !changed => previous.0,
+            _ => {
+                let global = parent_world * transform.matrix();
+                updates.push((entity, global));
+                global
+            },
+        };
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.0.iter().map(|child| (*child, global, changed)));
+        }
+    }
+
+    for (entity, global) in updates {
+        world.insert(entity, GlobalTransform(global));
+    }
+    world.storage_mut::<Transform>().clear_changed();
+}
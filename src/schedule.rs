@@ -0,0 +1,76 @@
+use crate::ecs::World;
+
+#[derive(Clone, Copy, Debug, Default)]
+// the frame's timing, updated by the engine before the schedule runs
+pub struct Time {
+    pub delta: f32,   // seconds since the last frame
+    #[allow(dead_code)]
+    pub elapsed: f32, // seconds since the game started
+    #[allow(dead_code)]
+    pub frame: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+// when a system runs in the frame, stages run in this order
+pub enum Stage {
+    Update,     // game logic
+    Physics,    // the engine moves rigid bodies and finds collisions
+    Late,       // game logic reacting to this frame's collisions
+    PostUpdate, // the engine propagates transforms and poses skeletons for rendering
+}
+
+pub type System = Box<dyn FnMut(&mut World)>;
+
+// the systems the engine runs every frame, in stage order, then in the order they were added
+// the world's commands are applied before the first stage and after each one
+pub struct Schedule {
+    systems: Vec<(Stage, String, System)>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Schedule { systems: Vec::new() }
+    }
+
+    // a schedule with the engine's own systems, that games add theirs to
+    pub fn with_engine_systems() -> Self {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Physics, "integrate rigid bodies", crate::physics::integrate_rigid_bodies);
+        schedule.add_system(Stage::Physics, "update transforms", crate::scene::update_transforms);
+        schedule.add_system(Stage::Physics, "detect collisions", crate::physics::detect_collisions);
+        schedule.add_system(Stage::PostUpdate, "update late transforms", crate::scene::update_transforms);
+        schedule.add_system(Stage::PostUpdate, "update animations", crate::animation::update_animations);
+        schedule
+    }
+
+    pub fn add_system(&mut self, stage: Stage, name: &str, system: impl FnMut(&mut World) + 'static) {
+        // after the last system of the same or an earlier stage
+        let index = self.systems.partition_point(|(other, _, _)| *other <= stage);
+        self.systems.insert(index, (stage, name.to_string(), Box::new(system)));
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        let count = self.systems.len();
+        self.systems.retain(|(_, other, _)| other != name);
+        self.systems.len() != count
+    }
+
+    #[allow(dead_code)]
+    pub fn system_names(&self) -> impl Iterator<Item = &str> {
+        self.systems.iter().map(|(_, name, _)| name.as_str())
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        world.apply_commands();
+        let mut current = None;
+        for (stage, _, system) in self.systems.iter_mut() {
+            if current.is_some_and(|current| current != *stage) {
+                world.apply_commands();
+            }
+            current = Some(*stage);
+            system(world);
+        }
+        world.apply_commands();
+    }
+}
@@ -0,0 +1,31 @@
+// uniforms set by the engine for every program
+// the blocks are shared between programs and updated once per frame, see uniform_buffer.rs
+
+#define MAX_LIGHTS 8
+
+layout (std140) uniform Camera {
+    mat4 u_projection_matrix;
+    mat4 u_view_matrix;
+    mat4 u_view_projection_matrix;
+    vec3 u_camera_position;
+    vec2 u_resolution;
+};
+
+struct Light {
+    int kind; // 0 for directional, 1 for spot
+    float angle;
+    float range;
+    vec3 position;
+    vec3 direction;
+};
+
+layout (std140) uniform Lights {
+    int u_light_count;
+    Light u_lights[MAX_LIGHTS];
+};
+
+layout (std140) uniform Time {
+    float u_time;
+    float u_delta_time;
+    int u_frame;
+};
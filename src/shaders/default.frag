@@ -0,0 +1,23 @@
+#version 330 core
+
+#include "shadows.glsl"
+
+in vec3 v_world_position;
+in vec4 v_color;
+in vec2 v_uv;
+
+out vec4 Color;
+
+uniform vec3 u_color;
+
+#ifdef DIFFUSE_TEXTURE
+uniform sampler2D u_diffuse_texture;
+#endif
+
+void main() {
+    vec4 albedo = vec4(u_color, 1.0) * v_color;
+#ifdef DIFFUSE_TEXTURE
+    albedo *= texture(u_diffuse_texture, v_uv);
+#endif
+    Color = vec4(albedo.rgb * (1.0 - shadowing(v_world_position)), albedo.a);
+}
@@ -1,15 +1,23 @@
 #version 330 core
 
+#include "common.glsl"
+#include "instancing.glsl"
+#include "skinning.glsl"
+
+// per-vertex attributes, locations must match graphics.rs
 layout (location = 0) in vec3 Position;
+layout (location = 7) in vec2 UV;
 
-uniform vec2 u_resolution;
-uniform mat4 u_model_matrix;
-uniform mat4 u_view_matrix;
-uniform mat4 u_projection_matrix;
+out vec3 v_world_position;
+out vec4 v_color;
+out vec2 v_uv;
 
 // executed in parallel for each vertex
 void main() {
-    vec4 uv = u_model_matrix * vec4(Position, 1.0); // 0.0 is z, 1.0 is w
+    vec4 uv = InstanceModel * skinning() * vec4(Position, 1.0); // 0.0 is z, 1.0 is w
+    v_world_position = uv.xyz;
+    v_color = InstanceColor;
+    v_uv = UV;
     uv = u_projection_matrix * u_view_matrix * uv;
 
     // make ((-1.0, -1.0), (1.0, -1.0)), (1.0, 1.0, (-1.0, 1.0)) a square always in the center of the viewport
@@ -21,4 +29,4 @@ void main() {
 
     // gl_Position = vec4(uv, 1.0);
     gl_Position = uv;
-}
\ No newline at end of file
+}
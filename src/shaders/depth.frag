@@ -0,0 +1,5 @@
+#version 330 core
+
+// only the depth buffer is written
+void main() {
+}
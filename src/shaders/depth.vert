@@ -0,0 +1,12 @@
+#version 330 core
+
+#include "instancing.glsl"
+#include "skinning.glsl"
+
+layout (location = 0) in vec3 Position;
+
+uniform mat4 u_light_space_matrix;
+
+void main() {
+    gl_Position = u_light_space_matrix * InstanceModel * skinning() * vec4(Position, 1.0);
+}
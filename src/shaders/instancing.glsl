@@ -0,0 +1,5 @@
+// per-instance vertex attributes, locations must match graphics.rs
+// every mesh is drawn instanced, so vertex shaders read the model matrix from here
+
+layout (location = 1) in mat4 InstanceModel;
+layout (location = 5) in vec4 InstanceColor;
@@ -0,0 +1,35 @@
+#version 330 core
+
+in vec2 v_uv;
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_resolution;
+uniform float u_threshold;
+uniform float u_intensity;
+uniform float u_radius;
+
+vec3 bright(vec2 uv) {
+    vec3 color = texture(u_texture, uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    return color * max(brightness - u_threshold, 0.0) / max(brightness, 0.0001);
+}
+
+// single pass approximation: gaussian-weighted sum of the bright parts of a sparse disc of samples
+void main() {
+    vec2 texel = u_radius / u_resolution;
+    vec3 glow = vec3(0.0);
+    float total = 0.0;
+
+    for (int x = -4; x <= 4; x++) {
+        for (int y = -4; y <= 4; y++) {
+            vec2 offset = vec2(x, y);
+            float weight = exp(-dot(offset, offset) / 8.0);
+            glow += bright(v_uv + offset * texel) * weight;
+            total += weight;
+        }
+    }
+
+    vec4 color = texture(u_texture, v_uv);
+    Color = vec4(color.rgb + glow / total * u_intensity, color.a);
+}
@@ -0,0 +1,19 @@
+#version 330 core
+
+in vec2 v_uv;
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform float u_brightness;
+uniform float u_contrast;
+uniform float u_saturation;
+uniform vec3 u_tint;
+
+void main() {
+    vec4 color = texture(u_texture, v_uv);
+    vec3 graded = color.rgb * u_tint + u_brightness;
+    graded = (graded - 0.5) * u_contrast + 0.5;
+    float luma = dot(graded, vec3(0.299, 0.587, 0.114));
+    graded = mix(vec3(luma), graded, u_saturation);
+    Color = vec4(clamp(graded, 0.0, 1.0), color.a);
+}
@@ -0,0 +1,59 @@
+#version 330 core
+
+in vec2 v_uv;
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_resolution;
+
+const float FXAA_SPAN_MAX = 8.0;
+const float FXAA_REDUCE_MUL = 1.0 / 8.0;
+const float FXAA_REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+// based on timothy lottes' fxaa 2 console algorithm
+void main() {
+    vec2 texel = 1.0 / u_resolution;
+
+    vec3 rgb_nw = texture(u_texture, v_uv + vec2(-1.0, -1.0) * texel).rgb;
+    vec3 rgb_ne = texture(u_texture, v_uv + vec2(1.0, -1.0) * texel).rgb;
+    vec3 rgb_sw = texture(u_texture, v_uv + vec2(-1.0, 1.0) * texel).rgb;
+    vec3 rgb_se = texture(u_texture, v_uv + vec2(1.0, 1.0) * texel).rgb;
+    vec4 center = texture(u_texture, v_uv);
+
+    float luma_nw = luma(rgb_nw);
+    float luma_ne = luma(rgb_ne);
+    float luma_sw = luma(rgb_sw);
+    float luma_se = luma(rgb_se);
+    float luma_m = luma(center.rgb);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // direction perpendicular to the local edge
+    vec2 dir = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        ((luma_nw + luma_sw) - (luma_ne + luma_se))
+    );
+
+    float dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    float inverse_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * inverse_dir_min, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * texel;
+
+    vec3 rgb_a = 0.5 * (
+        texture(u_texture, v_uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(u_texture, v_uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgb_b = rgb_a * 0.5 + 0.25 * (
+        texture(u_texture, v_uv + dir * -0.5).rgb +
+        texture(u_texture, v_uv + dir * 0.5).rgb);
+
+    float luma_b = luma(rgb_b);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        Color = vec4(rgb_a, center.a);
+    } else {
+        Color = vec4(rgb_b, center.a);
+    }
+}
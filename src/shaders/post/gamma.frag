@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 v_uv;
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform float u_gamma;
+
+void main() {
+    vec4 color = texture(u_texture, v_uv);
+    Color = vec4(pow(color.rgb, vec3(1.0 / u_gamma)), color.a);
+}
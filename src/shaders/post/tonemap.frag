@@ -0,0 +1,17 @@
+#version 330 core
+
+in vec2 v_uv;
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform float u_exposure;
+
+// filmic curve fitted to aces by krzysztof narkowicz
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec4 hdr = texture(u_texture, v_uv);
+    Color = vec4(aces(hdr.rgb * u_exposure), hdr.a);
+}
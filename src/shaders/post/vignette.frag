@@ -0,0 +1,15 @@
+#version 330 core
+
+in vec2 v_uv;
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform float u_strength;
+uniform float u_softness;
+
+void main() {
+    vec4 color = texture(u_texture, v_uv);
+    float distance = length(v_uv - 0.5) * 1.41421356;
+    float vignette = 1.0 - smoothstep(1.0 - u_softness, 1.0, distance) * u_strength;
+    Color = vec4(color.rgb * vignette, color.a);
+}
@@ -0,0 +1,52 @@
+// shadow map sampling, MAX_SHADOW_MAPS must match the constant in shadow.rs
+
+#define MAX_SHADOW_MAPS 4
+
+uniform bool u_receive_shadows;
+uniform int u_shadow_count;
+uniform float u_shadow_strength;
+uniform mat4 u_light_space_matrix[MAX_SHADOW_MAPS];
+uniform float u_shadow_bias[MAX_SHADOW_MAPS];
+uniform int u_shadow_pcf_radius[MAX_SHADOW_MAPS];
+uniform sampler2D u_shadow_map[MAX_SHADOW_MAPS];
+
+// fraction of percentage-closer samples around `world_position` that are occluded from light i
+float shadow(sampler2D shadow_map, int i, vec3 world_position) {
+    vec4 light_clip = u_light_space_matrix[i] * vec4(world_position, 1.0);
+    if (light_clip.w <= 0.0) {
+        return 0.0; // behind a spot light
+    }
+
+    vec3 coords = light_clip.xyz / light_clip.w * 0.5 + 0.5;
+    if (coords.z > 1.0 || any(lessThan(coords.xy, vec2(0.0))) || any(greaterThan(coords.xy, vec2(1.0)))) {
+        return 0.0; // outside the light's frustum
+    }
+
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    int radius = u_shadow_pcf_radius[i];
+    float occluded = 0.0;
+    float samples = 0.0;
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            float closest = texture(shadow_map, coords.xy + vec2(x, y) * texel).r;
+            occluded += coords.z - u_shadow_bias[i] > closest ? 1.0 : 0.0;
+            samples += 1.0;
+        }
+    }
+    return occluded / samples;
+}
+
+// how shadowed `world_position` is by the most occluding light, 0 if the object doesn't receive shadows
+float shadowing(vec3 world_position) {
+    float shadowed = 0.0;
+
+    // sampler arrays can only be indexed with constants in glsl 330
+    if (u_receive_shadows) {
+        if (u_shadow_count > 0) shadowed = max(shadowed, shadow(u_shadow_map[0], 0, world_position));
+        if (u_shadow_count > 1) shadowed = max(shadowed, shadow(u_shadow_map[1], 1, world_position));
+        if (u_shadow_count > 2) shadowed = max(shadowed, shadow(u_shadow_map[2], 2, world_position));
+        if (u_shadow_count > 3) shadowed = max(shadowed, shadow(u_shadow_map[3], 3, world_position));
+    }
+
+    return shadowed * u_shadow_strength;
+}
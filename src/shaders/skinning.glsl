@@ -0,0 +1,28 @@
+// skeletal animation, enabled by defining SKINNING
+// attribute locations must match graphics.rs, and MAX_JOINTS animation.rs
+
+#ifdef SKINNING
+
+#define MAX_JOINTS 64
+
+layout (location = 8) in uvec4 Joints;
+layout (location = 9) in vec4 Weights;
+
+// each joint's current transform relative to its bind pose, in model space
+uniform mat4 u_joint_matrices[MAX_JOINTS];
+
+// moves a model space position from the bind pose to the current pose
+mat4 skinning() {
+    return Weights.x * u_joint_matrices[Joints.x]
+         + Weights.y * u_joint_matrices[Joints.y]
+         + Weights.z * u_joint_matrices[Joints.z]
+         + Weights.w * u_joint_matrices[Joints.w];
+}
+
+#else
+
+mat4 skinning() {
+    return mat4(1.0);
+}
+
+#endif
@@ -0,0 +1,99 @@
+use crate::assets::{Assets, ProgramHandle};
+use crate::culling::Frustum;
+use crate::graphics::*;
+use crate::light::Light;
+use crate::renderer::{Drawable, Renderer};
+
+// must match MAX_SHADOW_MAPS in shaders/shadows.glsl
+pub const MAX_SHADOW_MAPS: usize = 4;
+
+// the first texture unit shadow maps are bound to, lower units are left for material textures
+const SHADOW_TEXTURE_UNIT: u32 = 8;
+
+// renders depth maps for shadow-casting lights and feeds them to the scene program
+pub struct ShadowRenderer {
+    program: ProgramHandle,
+    skinned_program: ProgramHandle,
+    maps: Vec<Framebuffer>,
+    active: Vec<Light>, // shadow-casting lights rendered this frame, in map order
+    pub strength: f32,  // how much light a fully shadowed fragment loses
+}
+
+impl ShadowRenderer {
+    // the depth programs are loaded through `assets`, so they're hot reloaded with the scene's
+    pub fn new(assets: &mut Assets) -> Result<Self, String> {
+        let program = assets.load_program("depth.vert", "depth.frag", &[])?;
+        let skinned_program = assets.load_program("depth.vert", "depth.frag", &["SKINNING"])?;
+        Ok(ShadowRenderer {
+            program,
+            skinned_program,
+            maps: Vec::new(),
+            active: Vec::new(),
+            strength: 0.6,
+        })
+    }
+
+    // renders a depth map for each shadow-casting light, leaving the default framebuffer bound
+    pub fn render(&mut self, lights: &[Light], objects: &[Drawable], renderer: &mut Renderer, assets: &Assets) -> Result<(), String> {
+        let (program, skinned_program) = (assets.program(self.program), assets.program(self.skinned_program));
+        self.active = lights.iter().filter(|light| light.shadows.is_some()).take(MAX_SHADOW_MAPS).copied().collect();
+
+        for (i, light) in self.active.iter().enumerate() {
+            let resolution = light.shadows.unwrap().resolution;
+            if i == self.maps.len() {
+                let map = Framebuffer::new(resolution, resolution, &[], Some(AttachmentKind::Texture(TextureFormat::Depth24)))?;
+                // everything outside the map is lit
+                map.depth_texture().unwrap().clamp_to_border([1., 1., 1., 1.]);
+                self.maps.push(map);
+            }
+            let map = &mut self.maps[i];
+            map.resize(resolution, resolution)?;
+            map.bind();
+
+            unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT); }
+            let light_space_matrix = light.view_projection();
+
+            // casters outside the camera's view can still shadow what's in it, so cull against the light instead
+            let frustum = Frustum::from_view_projection(&light_space_matrix);
+            let casters = || {
+                objects.iter().filter(|object| object.shadows.cast && frustum.intersects_aabb(&object.bounds))
+            };
+
+            program.set();
+            program.set_mat4("u_light_space_matrix", &light_space_matrix);
+            renderer.render_depth(casters());
+
+            skinned_program.set();
+            skinned_program.set_mat4("u_light_space_matrix", &light_space_matrix);
+            renderer.render_depth_skinned(casters(), skinned_program);
+        }
+
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0); }
+        Ok(())
+    }
+
+    // binds this frame's shadow maps and sets the shadow uniforms of the scene program, if it samples them
+    pub fn bind(&self, program: &Program) {
+        if !program.has_uniform("u_shadow_count") {
+            return;
+        }
+
+        program.set_int("u_shadow_count", self.active.len() as i32);
+        program.set_float("u_shadow_strength", self.strength);
+
+        for i in 0..MAX_SHADOW_MAPS {
+            // every sampler needs a unit even when unused
+            program.set_int(&format!("u_shadow_map[{}]", i), (SHADOW_TEXTURE_UNIT + i as u32) as i32);
+        }
+
+        for (i, light) in self.active.iter().enumerate() {
+            let settings = light.shadows.unwrap();
+            self.maps[i].depth_texture().unwrap().bind(SHADOW_TEXTURE_UNIT + i as u32);
+            program.set_mat4(&format!("u_light_space_matrix[{}]", i), &light.view_projection());
+            program.set_float(&format!("u_shadow_bias[{}]", i), settings.bias);
+            program.set_int(&format!("u_shadow_pcf_radius[{}]", i), settings.pcf_radius);
+        }
+
+        unsafe { gl::ActiveTexture(gl::TEXTURE0); }
+    }
+}
@@ -0,0 +1,138 @@
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// position, rotation and scale, applied as scale, then rotation, then translation
+// the matrix is rebuilt whenever they change, so reading it, like every propagation pass does, is free
+pub struct Transform {
+    translation: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+    matrix: Mat4,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+        matrix: Mat4::IDENTITY,
+    };
+
+    pub fn new(translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        let mut transform = Transform { translation, rotation, scale, matrix: Mat4::IDENTITY };
+        transform.update_matrix();
+        transform
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Transform::new(translation, Quat::IDENTITY, Vec3::ONE)
+    }
+
+    pub fn from_translation_scale(translation: Vec3, scale: Vec3) -> Self {
+        Transform::new(translation, Quat::IDENTITY, scale)
+    }
+
+    // shear can't be represented, so it's lost
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Transform::new(translation, rotation, scale)
+    }
+
+    fn update_matrix(&mut self) {
+        self.matrix = Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation);
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        self.matrix
+    }
+
+    #[allow(dead_code)]
+    pub fn translation(&self) -> Vec3 {
+        self.translation
+    }
+
+    #[allow(dead_code)]
+    pub fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    #[allow(dead_code)]
+    pub fn scale(&self) -> Vec3 {
+        self.scale
+    }
+
+    pub fn set_translation(&mut self, translation: Vec3) {
+        self.translation = translation;
+        self.update_matrix();
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation;
+        self.update_matrix();
+    }
+
+    pub fn set_scale(&mut self, scale: Vec3) {
+        self.scale = scale;
+        self.update_matrix();
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        self.set_translation(self.translation + offset);
+    }
+
+    // rotates in place, about the transform's own position
+    #[allow(dead_code)]
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.set_rotation((rotation * self.rotation).normalize());
+    }
+
+    // orbits `point`, turning to keep facing the same way relative to it
+    #[allow(dead_code)]
+    pub fn rotate_around(&mut self, point: Vec3, rotation: Quat) {
+        self.translation = point + rotation * (self.translation - point);
+        self.rotate(rotation);
+    }
+
+    // turns forward towards `target`, keeping up as close to `up` as possible
+    // nothing changes if the target is at the transform's position or straight along `up`
+    #[allow(dead_code)]
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let direction = target - self.translation;
+        if direction.cross(up).length_squared() <= f32::EPSILON {
+            return;
+        }
+        // look_to_rh builds a view rotation, its transpose turns the object instead
+        self.set_rotation(Quat::from_mat3(&Mat3::look_to_rh(direction.normalize(), up.normalize()).transpose()));
+    }
+
+    // -z, the direction cameras look along
+    #[allow(dead_code)]
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    #[allow(dead_code)]
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    #[allow(dead_code)]
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    // `t` of the way from this transform to `other`, along the shortest rotation
+    pub fn lerp(&self, other: &Transform, t: f32) -> Transform {
+        Transform::new(
+            self.translation.lerp(other.translation, t),
+            self.rotation.slerp(other.rotation, t),
+            self.scale.lerp(other.scale, t),
+        )
+    }
+}
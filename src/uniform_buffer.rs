@@ -0,0 +1,281 @@
+use std::{marker::PhantomData, ptr::null};
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+// binding points shared by every program, see Program::from_shaders
+pub const CAMERA_BINDING: GLuint = 0;
+pub const LIGHTS_BINDING: GLuint = 1;
+pub const TIME_BINDING: GLuint = 2;
+
+// uniform block names in the shaders and the binding points they are attached to
+pub const BLOCK_BINDINGS: &[(&str, GLuint)] = &[
+    ("Camera", CAMERA_BINDING),
+    ("Lights", LIGHTS_BINDING),
+    ("Time", TIME_BINDING),
+];
+
+// must match MAX_LIGHTS in shaders/common.glsl
+pub const MAX_LIGHTS: usize = 8;
+
+pub const fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+// a value that can be written into a uniform block with the std140 layout rules
+pub trait Std140 {
+    const ALIGN: usize; // base alignment in bytes
+    const SIZE: usize;  // bytes written, excluding trailing padding for scalars and vectors
+
+    // writes the value to the start of `out`, which is at least SIZE bytes long
+    fn write_std140(&self, out: &mut [u8]);
+}
+
+macro_rules! std140_scalar {
+    ($ty:ty, $convert:expr) => {
+        impl Std140 for $ty {
+            const ALIGN: usize = 4;
+            const SIZE: usize = 4;
+
+            fn write_std140(&self, out: &mut [u8]) {
+                out[..4].copy_from_slice(&$convert(*self).to_ne_bytes());
+            }
+        }
+    };
+}
+
+std140_scalar!(f32, |v: f32| v);
+std140_scalar!(i32, |v: i32| v);
+std140_scalar!(u32, |v: u32| v);
+std140_scalar!(bool, |v: bool| v as u32);
+
+macro_rules! std140_floats {
+    ($ty:ty, $align:expr, $size:expr, $to_array:ident) => {
+        impl Std140 for $ty {
+            const ALIGN: usize = $align;
+            const SIZE: usize = $size;
+
+            fn write_std140(&self, out: &mut [u8]) {
+                for (i, v) in self.$to_array().iter().enumerate() {
+                    out[i * 4..i * 4 + 4].copy_from_slice(&v.to_ne_bytes());
+                }
+            }
+        }
+    };
+}
+
+std140_floats!(Vec2, 8, 8, to_array);
+std140_floats!(Vec3, 16, 12, to_array);
+std140_floats!(Vec4, 16, 16, to_array);
+std140_floats!(Mat4, 16, 64, to_cols_array); // four vec4 columns
+
+// array elements are padded to a multiple of 16 bytes
+impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGN: usize = align_up(T::ALIGN, 16);
+    const SIZE: usize = align_up(T::SIZE, 16) * N;
+
+    fn write_std140(&self, out: &mut [u8]) {
+        let stride = align_up(T::SIZE, 16);
+        for (i, element) in self.iter().enumerate() {
+            element.write_std140(&mut out[i * stride..]);
+        }
+    }
+}
+
+// declares a struct and derives its std140 layout from the field order, so it can be uploaded to
+// a uniform block declared with the same members, or nested in another std140 struct
+macro_rules! std140_struct {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident { $($field_vis:vis $field:ident: $ty:ty),* $(,)? }) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        impl $crate::uniform_buffer::Std140 for $name {
+            const ALIGN: usize = 16;
+            const SIZE: usize = {
+                let mut offset = 0;
+                $(offset = $crate::uniform_buffer::align_up(offset, <$ty as $crate::uniform_buffer::Std140>::ALIGN) + <$ty as $crate::uniform_buffer::Std140>::SIZE;)*
+                $crate::uniform_buffer::align_up(offset, 16)
+            };
+
+            fn write_std140(&self, out: &mut [u8]) {
+                let mut offset = 0;
+                $(
+                    offset = $crate::uniform_buffer::align_up(offset, <$ty as $crate::uniform_buffer::Std140>::ALIGN);
+                    $crate::uniform_buffer::Std140::write_std140(&self.$field, &mut out[offset..]);
+                    offset += <$ty as $crate::uniform_buffer::Std140>::SIZE;
+                )*
+                let _ = offset;
+            }
+        }
+    };
+}
+
+std140_struct! {
+    // layout(std140) uniform Camera in shaders/common.glsl
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct CameraBlock {
+        pub projection_matrix: Mat4,
+        pub view_matrix: Mat4,
+        pub view_projection_matrix: Mat4,
+        pub position: Vec3,
+        pub resolution: Vec2,
+    }
+}
+
+std140_struct! {
+    // struct Light in shaders/common.glsl
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct LightData {
+        pub kind: i32, // 0 for directional, 1 for spot
+        pub angle: f32,
+        pub range: f32,
+        pub position: Vec3,
+        pub direction: Vec3,
+    }
+}
+
+std140_struct! {
+    // layout(std140) uniform Lights in shaders/common.glsl
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct LightsBlock {
+        pub count: i32,
+        pub lights: [LightData; MAX_LIGHTS],
+    }
+}
+
+std140_struct! {
+    // layout(std140) uniform Time in shaders/common.glsl
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct TimeBlock {
+        pub time: f32,
+        pub delta_time: f32,
+        pub frame: i32,
+    }
+}
+
+// a buffer backing a uniform block, bound to a fixed binding point so every program sees it
+pub struct UniformBuffer<T: Std140> {
+    pub id: GLuint,
+    data: Vec<u8>,
+    _block: PhantomData<T>,
+}
+
+impl<T: Std140> UniformBuffer<T> {
+    pub fn new(binding: GLuint) -> Self {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, id);
+            gl::BufferData(gl::UNIFORM_BUFFER, T::SIZE as gl::types::GLsizeiptr, null(), gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, id);
+        }
+        UniformBuffer {
+            id,
+            data: vec![0; T::SIZE],
+            _block: PhantomData,
+        }
+    }
+
+    // uploads `block`, meant to be called once per frame before drawing
+    pub fn update(&mut self, block: &T) {
+        self.data.fill(0);
+        block.write_std140(&mut self.data);
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.id);
+            gl::BufferSubData(gl::UNIFORM_BUFFER, 0, self.data.len() as gl::types::GLsizeiptr, self.data.as_ptr() as *const gl::types::GLvoid);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    pub fn delete(&self) {
+        unsafe { gl::DeleteBuffers(1, &self.id); }
+    }
+}
+
+impl<T: Std140> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        self.delete();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes<T: Std140>(block: &T) -> Vec<u8> {
+        let mut out = vec![0; T::SIZE];
+        block.write_std140(&mut out);
+        out
+    }
+
+    fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn i32_at(bytes: &[u8], offset: usize) -> i32 {
+        i32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    // the offsets below are worked out by hand from the std140 rules for the blocks in shaders/common.glsl
+
+    #[test]
+    fn camera_block_matches_std140() {
+        assert_eq!(CameraBlock::SIZE, 224);
+        let block = CameraBlock {
+            projection_matrix: Mat4::from_diagonal(Vec4::splat(1.)),
+            view_matrix: Mat4::from_diagonal(Vec4::splat(2.)),
+            view_projection_matrix: Mat4::from_diagonal(Vec4::splat(3.)),
+            position: Vec3::new(4., 5., 6.),
+            resolution: Vec2::new(7., 8.),
+        };
+        let bytes = bytes(&block);
+        assert_eq!(f32_at(&bytes, 0), 1.);
+        assert_eq!(f32_at(&bytes, 64 + 60), 2.);
+        assert_eq!(f32_at(&bytes, 128), 3.);
+        assert_eq!([192, 196, 200].map(|offset| f32_at(&bytes, offset)), [4., 5., 6.]);
+        // a vec2 after a vec3 starts at the next multiple of 8, not in the vec3's padding
+        assert_eq!(f32_at(&bytes, 204), 0.);
+        assert_eq!([208, 212].map(|offset| f32_at(&bytes, offset)), [7., 8.]);
+    }
+
+    #[test]
+    fn lights_block_matches_std140() {
+        assert_eq!(LightData::SIZE, 48);
+        assert_eq!(LightsBlock::SIZE, 16 + 48 * MAX_LIGHTS);
+
+        let mut block = LightsBlock { count: 2, ..Default::default() };
+        for (i, light) in block.lights.iter_mut().enumerate() {
+            let n = i as f32;
+            *light = LightData {
+                kind: i as i32,
+                angle: n + 0.25,
+                range: n + 0.5,
+                position: Vec3::splat(n + 1.),
+                direction: Vec3::splat(n + 2.),
+            };
+        }
+        let bytes = bytes(&block);
+        assert_eq!(i32_at(&bytes, 0), 2);
+        // the array is aligned to 16 bytes and each element is padded to 48
+        for i in 0..MAX_LIGHTS {
+            let (base, n) = (16 + 48 * i, i as f32);
+            assert_eq!(i32_at(&bytes, base), i as i32);
+            assert_eq!(f32_at(&bytes, base + 4), n + 0.25);
+            assert_eq!(f32_at(&bytes, base + 8), n + 0.5);
+            assert_eq!([16, 20, 24].map(|offset| f32_at(&bytes, base + offset)), [n + 1.; 3]);
+            assert_eq!([32, 36, 40].map(|offset| f32_at(&bytes, base + offset)), [n + 2.; 3]);
+        }
+    }
+
+    #[test]
+    fn time_block_matches_std140() {
+        assert_eq!(TimeBlock::SIZE, 16);
+        let bytes = bytes(&TimeBlock { time: 1.5, delta_time: 0.25, frame: 7 });
+        assert_eq!(f32_at(&bytes, 0), 1.5);
+        assert_eq!(f32_at(&bytes, 4), 0.25);
+        assert_eq!(i32_at(&bytes, 8), 7);
+    }
+}
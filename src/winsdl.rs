@@ -1,10 +1,9 @@
-use sdl2::{video::{gl_attr, GLContext, SwapInterval, Window}, EventPump, Sdl};
+use sdl2::{video::{GLContext, SwapInterval, Window}, EventPump, Sdl};
 
 pub struct Winsdl {
     pub sdl_context: Sdl,
     pub window: Window,
-    pub gl_context: GLContext,
-    pub gl: (),
+    _gl_context: GLContext, // held so the context lives as long as the window
     pub event_pump: EventPump,
 }
 
@@ -25,7 +24,7 @@ impl Winsdl {
             .unwrap();
 
         let gl_context = window.gl_create_context().unwrap(); // create opengl context
-        let gl = gl::load_with(|s| { // load opengl functions
+        gl::load_with(|s| { // load opengl functions
             video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void
         });
 
@@ -39,8 +38,7 @@ impl Winsdl {
         Ok(Winsdl {
             sdl_context,
             window,
-            gl_context,
-            gl,
+            _gl_context: gl_context,
             event_pump,
         })
     }